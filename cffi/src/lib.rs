@@ -11,7 +11,10 @@ use hycore::{
     },
     ext::hylog::LogLevelEXT,
     hywarn,
-    utils::{error::HyErrorType, opaque::OpaqueList},
+    utils::{
+        error::HyErrorType,
+        opaque::{OpaqueList, OpaqueListBuilder, OpaqueObject},
+    },
 };
 use strum::FromRepr;
 
@@ -20,7 +23,7 @@ pub struct HyModule(ModuleKey, Weak<InstanceContext>);
 
 /// cbindgen:rename-all=ScreamingSnakeCase
 #[repr(u32)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum HyResult {
     HyResultSuccess,
     HyResultInvalidPointer,
@@ -81,6 +84,7 @@ impl From<hycore::utils::error::HyError> for HyResult {
             HyErrorType::HyInstrError => HyResult::HyResultInstrError,
             HyErrorType::KeyNotFound => HyResult::HyResultKeyNotFound,
             HyErrorType::DuplicatedKey => HyResult::HyResultDuplicatedKey,
+            HyErrorType::UnexpectedOpaqueObject => HyResult::HyResultStructureTypeMismatch,
             HyErrorType::Unknown => HyResult::HyResultUnknown,
         }
     }
@@ -195,62 +199,64 @@ pub unsafe fn verify_structure_type<T>(element: *const T, expected: HyStructureT
     s_type == expected
 }
 
+unsafe fn convert_log_create_info_ext(
+    p_next: *const c_void,
+) -> hycore::utils::error::HyResult<(Box<dyn OpaqueObject>, *const c_void)> {
+    let log_create_info = unsafe { &*(p_next as *const HyLogCreateInfoEXT) };
+
+    let level: LogLevelEXT = log_create_info.level.into();
+    let callback = log_create_info.callback;
+
+    let create_info = hycore::ext::hylog::LogCreateInfoEXT {
+        level,
+        callback: hycore::ext::hylog::LogCallbackEXT(Box::new(move |msg| {
+            let message = CString::new(msg.message.clone()).unwrap_or_default();
+            let module = CString::new(msg.module.clone()).unwrap_or_default();
+            let file = CString::new(msg.file.clone().unwrap_or_default()).unwrap_or_default();
+            let thread_name =
+                CString::new(msg.thread_name.clone().unwrap_or_default()).unwrap_or_default();
+
+            let mut message = HyLogMessageEXT {
+                level: msg.level.into(),
+                time_stamp: msg.timepoint.and_utc().timestamp(),
+                message: message.as_ptr() as *const c_char,
+                module: module.as_ptr() as *const c_char,
+                file: file.as_ptr() as *const c_char,
+                line: msg.line.unwrap_or(0),
+                thread_name: thread_name.as_ptr() as *const c_char,
+                p_next: std::ptr::null_mut(),
+            };
+            let message_ptr: *mut HyLogMessageEXT = &mut message;
+            callback(message_ptr);
+        })),
+    };
+    Ok((Box::new(create_info), log_create_info.p_next))
+}
+
+hycore::register!(structure_converter HyStructureType::HyStructureTypeLogCreateInfoEXT => convert_log_create_info_ext);
+const _: () = assert!(
+    HyStructureType::HyStructureTypeLogCreateInfoEXT as u32
+        == hycore::ext::hylog::LOG_CREATE_INFO_STRUCTURE_TYPE
+);
+
 pub unsafe fn convert_opaque_list_from_next(
     mut p_next: *const c_void,
 ) -> Result<OpaqueList, HyResult> {
-    let mut list = vec![];
+    let mut builder = OpaqueListBuilder::new();
 
     while p_next != std::ptr::null() {
-        // Read p_next sType
-        let s_type = unsafe {
-            let s_type_ptr = p_next as *const HyStructureType;
-            *s_type_ptr
-        };
+        // Read p_next sType, as a raw value since extensions may define their own
+        let s_type = unsafe { *(p_next as *const u32) };
 
-        match s_type {
-            HyStructureType::HyStructureTypeLogCreateInfoEXT => {
-                let log_create_info = unsafe {
-                    let ptr = p_next as *const HyLogCreateInfoEXT;
-                    &*ptr
-                };
-
-                let level: LogLevelEXT = log_create_info.level.into();
-                let callback = log_create_info.callback;
-
-                let create_info = hycore::ext::hylog::LogCreateInfoEXT {
-                    level,
-                    callback: hycore::ext::hylog::LogCallbackEXT(Box::new(move |msg| {
-                        let message = CString::new(msg.message.clone()).unwrap_or_default();
-                        let module = CString::new(msg.module.clone()).unwrap_or_default();
-                        let file =
-                            CString::new(msg.file.clone().unwrap_or_default()).unwrap_or_default();
-                        let thread_name = CString::new(msg.thread_name.clone().unwrap_or_default())
-                            .unwrap_or_default();
-
-                        let mut message = HyLogMessageEXT {
-                            level: msg.level.into(),
-                            time_stamp: msg.timepoint.and_utc().timestamp(),
-                            message: message.as_ptr() as *const c_char,
-                            module: module.as_ptr() as *const c_char,
-                            file: file.as_ptr() as *const c_char,
-                            line: msg.line.unwrap_or(0),
-                            thread_name: thread_name.as_ptr() as *const c_char,
-                            p_next: std::ptr::null_mut(),
-                        };
-                        let message_ptr: *mut HyLogMessageEXT = &mut message;
-                        callback(message_ptr);
-                    })),
-                };
-                list.push(Box::new(create_info) as Box<dyn hycore::utils::opaque::OpaqueObject>);
-                p_next = log_create_info.p_next;
-            }
-            _ => {
-                return Err(HyResult::HyResultStructureTypeMismatch);
-            }
-        }
+        let converter = hycore::ext::find_structure_converter(s_type)
+            .ok_or(HyResult::HyResultStructureTypeMismatch)?;
+
+        let (object, next) = unsafe { converter(p_next)? };
+        builder.push_structure(s_type, object)?;
+        p_next = next;
     }
 
-    Ok(OpaqueList(list))
+    Ok(builder.build()?)
 }
 
 /// Retrieves information about the version of the Hycore library.
@@ -577,3 +583,131 @@ pub extern "C" fn hyDestroyModule(module: *mut HyModule) {
         drop(boxed_module);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    const TEST_STRUCTURE_TYPE: u32 = 0x20000000;
+
+    #[repr(C)]
+    struct TestCreateInfoEXT {
+        s_type: u32,
+        value: u32,
+        p_next: *const c_void,
+    }
+
+    #[derive(Debug)]
+    struct TestObject(u32);
+    impl OpaqueObject for TestObject {}
+
+    thread_local! {
+        /// Values converted by [`convert_test_create_info`] on the current (test) thread.
+        static CONVERTED: RefCell<Vec<u32>> = const { RefCell::new(Vec::new()) };
+    }
+
+    unsafe fn convert_test_create_info(
+        p_next: *const c_void,
+    ) -> hycore::utils::error::HyResult<(Box<dyn OpaqueObject>, *const c_void)> {
+        let info = unsafe { &*(p_next as *const TestCreateInfoEXT) };
+        CONVERTED.with_borrow_mut(|converted| converted.push(info.value));
+        Ok((Box::new(TestObject(info.value)), info.p_next))
+    }
+
+    hycore::register!(structure_type TEST_STRUCTURE_TYPE => TestObject);
+    hycore::register!(structure_converter TEST_STRUCTURE_TYPE => convert_test_create_info);
+
+    /// Structure type whose converter yields a [`TestObject`] instead of the
+    /// registered [`OtherTestObject`].
+    const MISTYPED_STRUCTURE_TYPE: u32 = TEST_STRUCTURE_TYPE + 2;
+
+    #[derive(Debug)]
+    struct OtherTestObject;
+    impl OpaqueObject for OtherTestObject {}
+
+    hycore::register!(structure_type MISTYPED_STRUCTURE_TYPE => OtherTestObject);
+    hycore::register!(structure_converter MISTYPED_STRUCTURE_TYPE => convert_test_create_info);
+
+    #[test]
+    fn registered_structure_type_is_converted() {
+        let info = TestCreateInfoEXT {
+            s_type: TEST_STRUCTURE_TYPE,
+            value: 7,
+            p_next: std::ptr::null(),
+        };
+        let mut list = unsafe { convert_opaque_list_from_next(&info as *const _ as _) }.unwrap();
+        assert_eq!(list.take_ext::<TestObject>().unwrap().0, 7);
+        assert_eq!(CONVERTED.take(), [7]);
+
+        let unknown = TestCreateInfoEXT {
+            s_type: TEST_STRUCTURE_TYPE + 1,
+            value: 0,
+            p_next: std::ptr::null(),
+        };
+        assert_eq!(
+            unsafe { convert_opaque_list_from_next(&unknown as *const _ as _) }.unwrap_err(),
+            HyResult::HyResultStructureTypeMismatch
+        );
+        assert!(CONVERTED.take().is_empty());
+    }
+
+    #[test]
+    fn mistyped_structure_is_rejected() {
+        let mistyped = TestCreateInfoEXT {
+            s_type: MISTYPED_STRUCTURE_TYPE,
+            value: 3,
+            p_next: std::ptr::null(),
+        };
+        let info = TestCreateInfoEXT {
+            s_type: TEST_STRUCTURE_TYPE,
+            value: 7,
+            p_next: &mistyped as *const _ as _,
+        };
+        assert_eq!(
+            unsafe { convert_opaque_list_from_next(&info as *const _ as _) }.unwrap_err(),
+            HyResult::HyResultStructureTypeMismatch
+        );
+        assert_eq!(CONVERTED.take(), [7, 3]);
+    }
+
+    #[test]
+    fn registered_structure_type_reaches_instance_creation() {
+        let test_info = TestCreateInfoEXT {
+            s_type: TEST_STRUCTURE_TYPE,
+            value: 100,
+            p_next: std::ptr::null(),
+        };
+        let application_info = HyApplicationInfo {
+            s_type: HyStructureType::HyStructureTypeApplicationInfo,
+            application_version: HyVersionInfo {
+                major: 0,
+                minor: 1,
+                patch: 0,
+            },
+            p_application_name: std::ptr::null(),
+            engine_version: HyVersionInfo {
+                major: 0,
+                minor: 1,
+                patch: 0,
+            },
+            p_engine_name: std::ptr::null(),
+        };
+        let create_info = HyInstanceCreateInfo {
+            s_type: HyStructureType::HyStructureTypeInstanceCreateInfo,
+            p_application_info: &application_info,
+            pp_enabled_extensions: std::ptr::null(),
+            enabled_extensions_count: 0,
+            node_id: 0,
+            p_next: &test_info as *const _ as *mut c_void,
+        };
+
+        let mut instance: *mut HyInstance = std::ptr::null_mut();
+        let result = hyCreateInstance(&create_info, &mut instance);
+        assert_eq!(result, HyResult::HyResultSuccess);
+        assert!(!instance.is_null());
+        assert_eq!(CONVERTED.take(), [100]);
+        hyDestroyInstance(instance);
+    }
+}
@@ -12,7 +12,10 @@ use crate::{
     ext::{DynPluginEXT, StaticPluginEXT, hylog::LogMessageEXT, load_plugin_by_name},
     hyerror, hyinfo, hytrace,
     theorems::library::TheoremLibrary,
-    utils::error::{HyError, HyResult},
+    utils::{
+        error::{HyError, HyResult},
+        opaque::OpaqueListBuilder,
+    },
 };
 
 pub mod api;
@@ -92,9 +95,23 @@ impl InstanceContext {
             .and_then(|wrapper| wrapper.downcast_ref())
     }
 
+    /// Iterates over the extensions enabled on this instance, yielding the UUID,
+    /// name, and version of each loaded plugin (ordered by UUID).
+    pub fn enabled_extensions(&self) -> impl Iterator<Item = (Uuid, &str, &Version)> {
+        self.extensions
+            .values()
+            .map(|plugin| (plugin.uuid(), plugin.name(), plugin.version()))
+    }
+
     /// Constructs a new [`InstanceContext`] and wires all enabled extensions
     /// into it.
     pub fn create(mut instance_create_info: api::InstanceCreateInfo) -> HyResult<Arc<Self>> {
+        let mut ext = OpaqueListBuilder::new().accept_structure_types();
+        for object in std::mem::take(&mut instance_create_info.ext.0) {
+            ext.push(object)?;
+        }
+        instance_create_info.ext = ext.build()?;
+
         // Construct state about the application.
         let application_name = instance_create_info.application_info.application_name;
         let application_version = instance_create_info
@@ -302,3 +319,43 @@ impl InstanceContext {
         }
     }
 }
+
+#[cfg(all(test, feature = "ext_hylog"))]
+mod tests {
+    use super::*;
+    use crate::{
+        base::api::{ApplicationInfo, InstanceCreateInfo, VersionInfo},
+        magic::{HYPERION_LOGGER_NAME_EXT, HYPERION_LOGGER_UUID_EXT},
+    };
+
+    #[test]
+    fn enabled_extensions_lists_logger() {
+        let version = VersionInfo {
+            major: 0,
+            minor: 1,
+            patch: 0,
+        };
+        let instance = InstanceContext::create(InstanceCreateInfo {
+            application_info: ApplicationInfo {
+                application_version: version,
+                application_name: "test".to_string(),
+                engine_version: version,
+                engine_name: "test".to_string(),
+            },
+            enabled_extensions: vec![HYPERION_LOGGER_NAME_EXT.to_string()],
+            node_id: 0,
+            ext: Default::default(),
+        })
+        .unwrap();
+
+        let extensions: Vec<_> = instance.enabled_extensions().collect();
+        assert_eq!(
+            extensions,
+            vec![(
+                HYPERION_LOGGER_UUID_EXT,
+                HYPERION_LOGGER_NAME_EXT,
+                &Version::new(0, 1, 0)
+            )]
+        );
+    }
+}
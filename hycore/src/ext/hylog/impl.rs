@@ -8,6 +8,7 @@ use crate::{
     register,
     utils::{error::HyResult, opaque::OpaqueList},
 };
+use semver::Version;
 use std::sync::Weak;
 use uuid::Uuid;
 
@@ -24,6 +25,7 @@ impl StaticPluginEXT for LogPluginEXT {
     const UUID: Uuid = HYPERION_LOGGER_UUID_EXT;
     const NAME: &'static str = HYPERION_LOGGER_NAME_EXT;
     const DESCRIPTION: &'static str = "Hyperion Logger Extension";
+    const VERSION: &'static Version = &Version::new(0, 1, 0);
 
     fn new(ext: &mut OpaqueList) -> Self {
         // Find the LogCreateInfoEXT in the ext list
@@ -8,7 +8,7 @@ mod r#impl;
 use pyo3::prelude::*;
 use strum::FromRepr;
 
-use crate::{define_py_opaque_object_loaders, utils::opaque::OpaqueObject};
+use crate::{define_py_opaque_object_loaders, register, utils::opaque::OpaqueObject};
 
 /// Formats a message and forwards it to the runtime log callback registered on
 /// an [`InstanceContext`](crate::base::InstanceContext).
@@ -208,6 +208,9 @@ impl<'a, 'py> FromPyObject<'a, 'py> for LogCallbackEXT {
     }
 }
 
+/// Structure type identifying [`LogCreateInfoEXT`] in extension chains.
+pub const LOG_CREATE_INFO_STRUCTURE_TYPE: u32 = 0x1000_0000;
+
 /// Creation information for the logger extension.
 #[cfg_attr(feature = "pyo3", derive(FromPyObject))]
 #[derive(Debug)]
@@ -216,4 +219,5 @@ pub struct LogCreateInfoEXT {
     pub callback: LogCallbackEXT,
 }
 impl OpaqueObject for LogCreateInfoEXT {}
+register!(structure_type LOG_CREATE_INFO_STRUCTURE_TYPE => LogCreateInfoEXT);
 define_py_opaque_object_loaders!("hypi.api.ext.hylog.LogCreateInfoEXT", LogCreateInfoEXT);
@@ -12,21 +12,26 @@
 //! Between those pieces the host can safely preload libraries, wire per-instance configuration via
 //! [`ExtList`](crate::utils::conf::ExtList), and drop extensions deterministically. Refer to
 //! `docs/PluginSystem.md` for an end-to-end narrative.
-use std::sync::Weak;
+use std::{any::TypeId, ffi::c_void, sync::Weak};
 
 use downcast_rs::{DowncastSync, impl_downcast};
+use semver::Version;
 use uuid::Uuid;
 
 use crate::{
     base::InstanceContext,
     utils::{
         error::{HyError, HyResult},
-        opaque::OpaqueList,
+        opaque::{OpaqueList, OpaqueObject},
     },
 };
 
 pub mod hylog;
 
+/// Sentinel version reported by plugins that do not declare
+/// [`StaticPluginEXT::VERSION`].
+pub const UNVERSIONED_PLUGIN: Version = Version::new(0, 0, 0);
+
 /// Function signature to construct a plugin instance from a loaded shared object.
 pub type ExtLoaderFn = fn(&mut OpaqueList) -> HyResult<Box<dyn DynPluginEXT>>;
 
@@ -77,6 +82,72 @@ pub fn load_plugin_by_name(
     Err(HyError::PluginNotFound(name.to_string()))
 }
 
+/// Function signature converting one C extension structure into its Rust
+/// counterpart. Returns the converted object along with the structure's
+/// `p_next` pointer.
+///
+/// # Safety
+///
+/// `p_next` must point to a valid structure whose leading `u32` is the
+/// structure type the converter was registered for.
+pub type StructureConverterFn =
+    unsafe fn(p_next: *const c_void) -> HyResult<(Box<dyn OpaqueObject>, *const c_void)>;
+
+/// Static registration entry mapping a C structure type to its converter.
+pub struct StructureConverterRegistry {
+    pub s_type: u32,
+    pub converter: StructureConverterFn,
+}
+inventory::collect!(StructureConverterRegistry);
+
+/// Macro to register the converter of a C extension structure type.
+#[macro_export]
+macro_rules! register_structure_converter {
+    (
+        $s_type:expr => $converter:expr
+    ) => {
+        $crate::inventory::submit! {
+            $crate::ext::StructureConverterRegistry {
+                s_type: $s_type as u32,
+                converter: $converter,
+            }
+        }
+    };
+}
+
+/// Static registration entry binding a structure type to the concrete
+/// [`OpaqueObject`] it stands for. Both the C and the Rust entry points use it to
+/// check the objects handed to an instance, see
+/// [`OpaqueListBuilder`](crate::utils::opaque::OpaqueListBuilder).
+pub struct StructureTypeRegistry {
+    pub s_type: u32,
+    pub object_type: fn() -> TypeId,
+}
+inventory::collect!(StructureTypeRegistry);
+
+/// Macro to register the [`OpaqueObject`] type of a structure type.
+#[macro_export]
+macro_rules! register_structure_type {
+    (
+        $s_type:expr => $object:ty
+    ) => {
+        $crate::inventory::submit! {
+            $crate::ext::StructureTypeRegistry {
+                s_type: $s_type as u32,
+                object_type: ::std::any::TypeId::of::<$object>,
+            }
+        }
+    };
+}
+
+/// Looks up the converter registered for the structure type `s_type`.
+pub fn find_structure_converter(s_type: u32) -> Option<StructureConverterFn> {
+    inventory::iter::<StructureConverterRegistry>
+        .into_iter()
+        .find(|registry| registry.s_type == s_type)
+        .map(|registry| registry.converter)
+}
+
 /// Plugin trait defining the lifecycle hooks and behaviors every Hyperion
 /// extension must implement.
 pub trait PluginEXT {
@@ -106,6 +177,10 @@ impl<T: PluginEXT + StaticPluginEXT> DynPluginEXT for T {
     fn description(&self) -> &str {
         T::DESCRIPTION
     }
+
+    fn version(&self) -> &Version {
+        T::VERSION
+    }
 }
 
 /// Runtime contract that every Hyperion plugin must honor.
@@ -120,6 +195,10 @@ pub trait DynPluginEXT: DowncastSync + PluginEXT {
     /// Longer description explaining the functionality or requirements of the
     /// plugin.
     fn description(&self) -> &str;
+
+    /// Version of the plugin implementation. For statically registered plugins
+    /// this is [`StaticPluginEXT::VERSION`].
+    fn version(&self) -> &Version;
 }
 impl_downcast!(sync DynPluginEXT);
 
@@ -136,6 +215,11 @@ pub trait StaticPluginEXT: DynPluginEXT {
     /// Human readable description surfaced to hosts.
     const DESCRIPTION: &'static str;
 
+    /// Version of the plugin implementation, returned as is by
+    /// [`DynPluginEXT::version`]. Defaults to [`UNVERSIONED_PLUGIN`] for plugins
+    /// that do not report one.
+    const VERSION: &'static Version = &UNVERSIONED_PLUGIN;
+
     /// Constructs a fresh plugin instance. Implementations should keep
     /// allocation lightweight because this is called on every load.
     fn new(ext: &mut OpaqueList) -> Self;
@@ -24,4 +24,10 @@ macro_rules! register {
     (derivation_strategy $ty:ty) => {
         $crate::register_derivation_strategy!($ty);
     };
+    (structure_type $s_type:expr => $ty:ty) => {
+        $crate::register_structure_type!($s_type => $ty);
+    };
+    (structure_converter $s_type:expr => $converter:expr) => {
+        $crate::register_structure_converter!($s_type => $converter);
+    };
 }
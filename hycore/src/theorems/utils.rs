@@ -30,43 +30,47 @@ pub fn simple_simplify_function(func: &mut Function) -> HyResult<()> {
     let mut previous_operand_map: HashMap<HyInstr, Option<Name>> = HashMap::new();
     let mut remapped_name: HashMap<Name, Name> = HashMap::new();
 
-    for (_, block) in func.body.iter_mut() {
-        previous_operand_map.clear();
+    let mut current_block = None;
+
+    // First, remap operands according to previous remappings
+    func.retain_instructions(|instr_ref, elem| {
+        // Merging only happens within a block
+        if current_block != Some(instr_ref.block) {
+            current_block = Some(instr_ref.block);
+            previous_operand_map.clear();
+        }
 
-        // First, remap operands according to previous remappings
-        block.instructions.retain(|elem| {
-            // non-simple instruction are ignored
-            if !elem.is_simple() {
-                return true;
-            }
+        // non-simple instruction are ignored
+        if !elem.is_simple() {
+            return true;
+        }
 
-            // Clone the element and set its destination to the same value (so we can compare)
-            let mut cloned_elem = elem.clone();
-            cloned_elem.set_destination(Name(0));
-
-            match previous_operand_map.get(&cloned_elem) {
-                Some(existing_name) => {
-                    // Remap the result name to the existing one
-                    let result = elem.destination();
-                    assert!(
-                        result.is_some() == existing_name.is_some(),
-                        "Instruction result presence mismatch"
-                    );
-                    if let (Some(result_name), Some(existing_name)) = (result, existing_name) {
-                        remapped_name.insert(result_name, *existing_name);
-                    }
-
-                    false
-                }
-                None => {
-                    // Insert the new operand into the hash map
-                    let result = elem.destination();
-                    previous_operand_map.insert(cloned_elem, result);
-                    true
+        // Clone the element and set its destination to the same value (so we can compare)
+        let mut cloned_elem = elem.clone();
+        cloned_elem.set_destination(Name(0));
+
+        match previous_operand_map.get(&cloned_elem) {
+            Some(existing_name) => {
+                // Remap the result name to the existing one
+                let result = elem.destination();
+                assert!(
+                    result.is_some() == existing_name.is_some(),
+                    "Instruction result presence mismatch"
+                );
+                if let (Some(result_name), Some(existing_name)) = (result, existing_name) {
+                    remapped_name.insert(result_name, *existing_name);
                 }
+
+                false
             }
-        });
-    }
+            None => {
+                // Insert the new operand into the hash map
+                let result = elem.destination();
+                previous_operand_map.insert(cloned_elem, result);
+                true
+            }
+        }
+    });
 
     // Finally, apply remapping to all instructions
     for (_, block) in func.body.iter_mut() {
@@ -152,19 +156,10 @@ pub fn remove_unused_op(func: &mut Function) -> HyResult<()> {
     }
 
     // Remove unreachable instructions
-    for (block_label, block) in func.body.iter_mut() {
-        let mut instr_index = 0;
-        block.instructions.retain(|_| {
-            let instr_ref = InstructionRef {
-                block: *block_label,
-                index: instr_index,
-                reserved: 0, // not used
-            };
-            instr_index += 1;
-            let node_index = refs_to_node[&instr_ref];
-            reachable_nodes.contains(node_index.index())
-        });
-    }
+    func.retain_instructions(|instr_ref, _| {
+        let node_index = refs_to_node[&instr_ref];
+        reachable_nodes.contains(node_index.index())
+    });
 
     // Verify the function after modifications
     Ok(())
@@ -35,6 +35,9 @@ pub enum HyError {
 
     #[error("Duplicated {context} with key '{key}'")]
     DuplicatedKey { key: String, context: String },
+
+    #[error("Unexpected opaque object of type '{0}'")]
+    UnexpectedOpaqueObject(String),
 }
 
 /// Convenience alias for fallible operations returning [`HyError`].
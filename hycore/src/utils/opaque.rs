@@ -1,14 +1,24 @@
 //! Utilities that allow extensions to pass opaque configuration objects across
 //! the FFI boundary (notably from Python) into Rust plugins.
-use std::fmt::Debug;
+use std::{any::TypeId, collections::BTreeSet, fmt::Debug};
 
 use downcast_rs::{DowncastSync, impl_downcast};
 #[cfg(feature = "pyo3")]
 use pyo3::{FromPyObject, PyAny, PyResult};
 
+use crate::{
+    ext::StructureTypeRegistry,
+    utils::error::{HyError, HyResult},
+};
+
 /// Marker trait implemented by per-extension configuration structs that need to
 /// cross API boundaries without the host knowing their concrete type upfront.
-pub trait OpaqueObject: DowncastSync + Debug {}
+pub trait OpaqueObject: DowncastSync + Debug {
+    /// Name of the concrete type, used in diagnostics.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
 impl_downcast!(sync OpaqueObject);
 
 #[cfg(feature = "pyo3")]
@@ -73,6 +83,93 @@ impl OpaqueList {
             None
         }
     }
+
+    /// Check that the list holds at most one object of each concrete type.
+    ///
+    /// Extensions retrieve their configuration through [`OpaqueList::take_ext`], which
+    /// only considers the first matching entry. Duplicates would be silently ignored.
+    pub fn validate(&self) -> HyResult<()> {
+        let mut seen = BTreeSet::new();
+        for ext in &self.0 {
+            if !seen.insert((**ext).as_any().type_id()) {
+                return Err(HyError::DuplicatedKey {
+                    key: ext.type_name().to_string(),
+                    context: "opaque object".to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builder for an [`OpaqueList`] only accepting a fixed set of object types.
+///
+/// This is the Rust counterpart of the `sType` checks performed on `pNext` chains by
+/// the C API: objects whose type was not accepted, or which do not match the type
+/// registered for their structure type, are rejected with
+/// [`HyError::UnexpectedOpaqueObject`].
+#[derive(Debug, Default)]
+pub struct OpaqueListBuilder {
+    accepted: Vec<TypeId>,
+    list: OpaqueList,
+}
+
+impl OpaqueListBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept objects of type `T` in the list.
+    pub fn accept<T: OpaqueObject>(mut self) -> Self {
+        self.accepted.push(TypeId::of::<T>());
+        self
+    }
+
+    /// Accept every object type registered with `register!(structure_type ...)`.
+    pub fn accept_structure_types(mut self) -> Self {
+        self.accepted.extend(
+            inventory::iter::<StructureTypeRegistry>
+                .into_iter()
+                .map(|registry| (registry.object_type)()),
+        );
+        self
+    }
+
+    /// Append an object to the list, failing if its type was not accepted.
+    pub fn push(&mut self, ext: Box<dyn OpaqueObject>) -> HyResult<&mut Self> {
+        if !self.accepted.contains(&(*ext).as_any().type_id()) {
+            return Err(HyError::UnexpectedOpaqueObject(ext.type_name().to_string()));
+        }
+        self.list.0.push(ext);
+        Ok(self)
+    }
+
+    /// Append an object read from a structure of type `s_type`, failing if it is
+    /// not of the type registered for `s_type`.
+    pub fn push_structure(
+        &mut self,
+        s_type: u32,
+        ext: Box<dyn OpaqueObject>,
+    ) -> HyResult<&mut Self> {
+        let expected = inventory::iter::<StructureTypeRegistry>
+            .into_iter()
+            .find(|registry| registry.s_type == s_type)
+            .ok_or_else(|| HyError::KeyNotFound {
+                key: format!("{s_type:#x}"),
+                context: "structure type".to_string(),
+            })?;
+        if (expected.object_type)() != (*ext).as_any().type_id() {
+            return Err(HyError::UnexpectedOpaqueObject(ext.type_name().to_string()));
+        }
+        self.list.0.push(ext);
+        Ok(self)
+    }
+
+    /// Finish the list, see [`OpaqueList::validate`].
+    pub fn build(self) -> HyResult<OpaqueList> {
+        self.list.validate()?;
+        Ok(self.list)
+    }
 }
 
 #[cfg(feature = "pyo3")]
@@ -111,3 +208,67 @@ impl<'a, 'py> FromPyObject<'a, 'py> for Box<dyn OpaqueObject> {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct FirstCreateInfo;
+    impl OpaqueObject for FirstCreateInfo {}
+
+    #[derive(Debug)]
+    struct SecondCreateInfo;
+    impl OpaqueObject for SecondCreateInfo {}
+
+    const FIRST_STRUCTURE_TYPE: u32 = 0x2000_0001;
+    crate::register!(structure_type FIRST_STRUCTURE_TYPE => FirstCreateInfo);
+
+    #[test]
+    fn builder_accepts_expected_types() {
+        let mut builder = OpaqueListBuilder::new()
+            .accept_structure_types()
+            .accept::<SecondCreateInfo>();
+        builder
+            .push_structure(FIRST_STRUCTURE_TYPE, Box::new(FirstCreateInfo))
+            .unwrap()
+            .push(Box::new(SecondCreateInfo))
+            .unwrap();
+
+        let mut list = builder.build().unwrap();
+        assert!(list.take_ext::<SecondCreateInfo>().is_some());
+        assert!(list.take_ext::<FirstCreateInfo>().is_some());
+    }
+
+    #[test]
+    fn builder_rejects_wrongly_typed_objects() {
+        let mut builder = OpaqueListBuilder::new().accept_structure_types();
+        assert!(matches!(
+            builder.push_structure(FIRST_STRUCTURE_TYPE, Box::new(SecondCreateInfo)),
+            Err(HyError::UnexpectedOpaqueObject(name)) if name.ends_with("SecondCreateInfo")
+        ));
+        assert!(matches!(
+            builder.push(Box::new(SecondCreateInfo)),
+            Err(HyError::UnexpectedOpaqueObject(_))
+        ));
+        assert!(matches!(
+            builder.push_structure(FIRST_STRUCTURE_TYPE + 1, Box::new(FirstCreateInfo)),
+            Err(HyError::KeyNotFound { .. })
+        ));
+        assert!(builder.build().unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn validate_rejects_duplicated_types() {
+        let mut list = OpaqueList(vec![Box::new(FirstCreateInfo), Box::new(SecondCreateInfo)]);
+        assert!(list.validate().is_ok());
+        assert!(list.take_ext::<SecondCreateInfo>().is_some());
+        assert!(list.take_ext::<FirstCreateInfo>().is_some());
+
+        let list = OpaqueList(vec![Box::new(FirstCreateInfo), Box::new(FirstCreateInfo)]);
+        assert!(matches!(
+            list.validate(),
+            Err(HyError::DuplicatedKey { key, .. }) if key.ends_with("FirstCreateInfo")
+        ));
+    }
+}
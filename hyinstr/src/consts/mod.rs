@@ -60,7 +60,7 @@ impl AnyConst {
                             if let Some(module) = self.module {
                                 if let Some(func) = module.functions.get(uuid) {
                                     if let Some(name) = &func.name {
-                                        write!(f, "ptr %{}", name)
+                                        write!(f, "ptr {}", name)
                                     } else {
                                         write!(f, "ptr @{:?}", uuid)
                                     }
@@ -74,7 +74,7 @@ impl AnyConst {
                         FunctionPointer::External(name) => {
                             if let Some(module) = self.module {
                                 if let Some(func) = module.external_functions.get(name) {
-                                    write!(f, "ptr external %{}", func.name)
+                                    write!(f, "ptr external {}", func.name)
                                 } else {
                                     write!(f, "ptr external <invalid@{}>", name)
                                 }
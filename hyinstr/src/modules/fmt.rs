@@ -2,13 +2,14 @@
 use crate::{
     analysis::{AnalysisStatistic, TerminationScope},
     modules::{
-        Function, Module,
+        CallingConvention, Function, Module,
         instructions::{
             HyInstr, Instruction,
             int::{IDiv, IRem},
             meta::MetaProbOperand,
         },
         operand::{Label, Operand},
+        symbol::ExternalFunction,
         terminator::HyTerminator,
     },
     types::TypeRegistry,
@@ -120,8 +121,8 @@ impl HyInstr {
                         Ok(false)
                     }
                     HyInstr::MLoad(load) => {
-                        if load.volatile {
-                            write!(f, "volatile ")?;
+                        if let Some(ordering) = &load.ordering {
+                            write!(f, ".{}", ordering.to_str())?;
                         }
 
                         write!(
@@ -130,34 +131,34 @@ impl HyInstr {
                             load.addr.fmt_with(Some(self.registry), self.module),
                         )?;
 
-                        if let Some(ordering) = &load.ordering {
-                            write!(f, ", atomic {}", ordering.to_str())?;
-                        }
-
                         if let Some(alignment) = load.alignement {
                             write!(f, ", align {}", alignment)?;
                         }
 
+                        if load.volatile {
+                            write!(f, ", volatile")?;
+                        }
+
                         Ok(true)
                     }
                     HyInstr::MStore(store) => {
-                        if store.volatile {
-                            write!(f, "volatile ")?;
+                        if let Some(ordering) = &store.ordering {
+                            write!(f, ".{}", ordering.to_str())?;
                         }
 
                         write!(
                             f,
-                            "{}, {}",
+                            " {}, {}",
                             store.addr.fmt_with(Some(self.registry), self.module),
                             store.value.fmt_with(Some(self.registry), self.module)
                         )?;
 
-                        if let Some(ordering) = &store.ordering {
-                            write!(f, ", atomic {}", ordering.to_str())?;
+                        if let Some(alignment) = store.alignement {
+                            write!(f, ", align {}", alignment)?;
                         }
 
-                        if let Some(alignment) = store.alignement {
-                            write!(f, ", align {} ", alignment)?;
+                        if store.volatile {
+                            write!(f, ", volatile")?;
                         }
 
                         Ok(true)
@@ -165,13 +166,12 @@ impl HyInstr {
                     HyInstr::MAlloca(malloca) => {
                         write!(
                             f,
-                            "{} {}",
-                            self.registry.fmt(malloca.ty),
+                            " {}",
                             malloca.count.fmt_with(Some(self.registry), self.module)
                         )?;
 
                         if let Some(alignment) = malloca.alignement {
-                            write!(f, ", align {} ", alignment)?;
+                            write!(f, ", align {}", alignment)?;
                         }
 
                         Ok(true)
@@ -347,7 +347,7 @@ impl HyTerminator {
                         cbranch.target_false
                     ),
                     HyTerminator::Jump(jump) => {
-                        write!(f, "jump label {}", jump.target)
+                        write!(f, "jump {}", jump.target)
                     }
                     HyTerminator::Ret(ret) => {
                         if let Some(value) = &ret.value {
@@ -388,16 +388,16 @@ impl Function {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 write!(
                     f,
-                    "define{} {} {}{}",
+                    "define {}{} {}{}",
+                    self.function
+                        .return_type
+                        .map(|ty| self.type_registry.fmt(ty).to_string())
+                        .unwrap_or("void".to_string()),
                     self.function
                         .cconv
                         .as_ref()
                         .map(|cc| format!(" {}", cc.to_string()))
                         .unwrap_or_default(),
-                    self.function
-                        .return_type
-                        .map(|ty| self.type_registry.fmt(ty).to_string())
-                        .unwrap_or("void".to_string()),
                     if self.function.meta_function { "!" } else { "" },
                     self.function
                         .name
@@ -414,12 +414,7 @@ impl Function {
                     } else {
                         write!(f, ", ")?;
                     }
-                    write!(
-                        f,
-                        "%{}: {}",
-                        param_name,
-                        self.type_registry.fmt(*param_type)
-                    )?;
+                    write!(f, "{}: {}", param_name, self.type_registry.fmt(*param_type))?;
                 }
                 writeln!(f, ") {{")?;
 
@@ -449,6 +444,41 @@ impl Function {
     }
 }
 
+impl ExternalFunction {
+    /// Build a formatting helper that renders the function as a `declare` statement.
+    pub fn fmt<'a>(&'a self, type_registry: &'a TypeRegistry) -> impl std::fmt::Display + 'a {
+        struct Fmt<'a> {
+            function: &'a ExternalFunction,
+            type_registry: &'a TypeRegistry,
+        }
+
+        impl<'a> std::fmt::Display for Fmt<'a> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self.function.return_type {
+                    Some(ty) => write!(f, "declare {}", self.type_registry.fmt(ty))?,
+                    None => write!(f, "declare void")?,
+                }
+                if self.function.cconv != CallingConvention::default() {
+                    write!(f, " {}", self.function.cconv.to_string())?;
+                }
+                write!(f, " external {}(", self.function.name)?;
+                for (index, ty) in self.function.param_types.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", self.type_registry.fmt(*ty))?;
+                }
+                write!(f, ")")
+            }
+        }
+
+        Fmt {
+            function: self,
+            type_registry,
+        }
+    }
+}
+
 impl Module {
     /// Build a formatting helper that renders every function within the module.
     pub fn fmt<'a>(&'a self, type_registry: &'a TypeRegistry) -> impl std::fmt::Display + 'a {
@@ -459,6 +489,9 @@ impl Module {
 
         impl<'a> std::fmt::Display for Fmt<'a> {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                for external in self.module.external_functions.values() {
+                    writeln!(f, "{}", external.fmt(self.type_registry))?;
+                }
                 for function in self.module.functions.values() {
                     writeln!(f, "{}", function.fmt(self.type_registry, Some(self.module)))?;
                 }
@@ -471,4 +504,28 @@ impl Module {
             type_registry,
         }
     }
+
+    /// Render the module as source text accepted by
+    /// [`crate::modules::parser::extend_module_from_string`].
+    ///
+    /// External functions are declared first, then functions are printed in
+    /// [`Module::functions_by_name`] order. Parsing the output back yields a module
+    /// without differences (see [`Module::diff`]).
+    pub fn to_source(&self, type_registry: &TypeRegistry) -> String {
+        let mut source = String::new();
+        let mut externals: Vec<_> = self.external_functions.values().collect();
+        externals.sort_by(|a, b| a.name.cmp(&b.name));
+        for external in &externals {
+            source.push_str(&external.fmt(type_registry).to_string());
+            source.push('\n');
+        }
+        if !externals.is_empty() {
+            source.push('\n');
+        }
+        for function in self.functions_by_name() {
+            source.push_str(&function.fmt(type_registry, Some(self)).to_string());
+            source.push('\n');
+        }
+        source
+    }
 }
@@ -6,7 +6,7 @@ use strum::{EnumDiscriminants, EnumIs, EnumIter, EnumTryAs, IntoEnumIterator};
 
 use crate::{
     modules::{Operand, operand::Name},
-    types::Typeref,
+    types::{AnyType, TypeRegistry, Typeref},
 };
 
 pub mod fp;
@@ -167,6 +167,19 @@ pub trait Instruction {
         }
     }
 
+    /// Replace every register operand referring to `name` with `replacement`.
+    ///
+    /// Unlike [`Instruction::remap_operands`], the replacement may be any operand
+    /// (e.g., an immediate), which makes this the building block for constant
+    /// propagation.
+    fn substitute_operand(&mut self, name: Name, replacement: Operand) {
+        for operand in self.operands_mut() {
+            if matches!(operand, Operand::Reg(reg) if *reg == name) {
+                *operand = replacement.clone();
+            }
+        }
+    }
+
     /// Remap types according to a mapping
     fn remap_types(&mut self, mapping: impl Fn(Typeref) -> Option<Typeref>) {
         for ty in self.referenced_types_mut() {
@@ -328,6 +341,21 @@ impl HyInstrOp {
         }
     }
 
+    /// Return the minimum operand count of the instruction.
+    ///
+    /// This is the fixed [`HyInstrOp::arity`] when there is one, otherwise the
+    /// documented minimum of the variadic instruction.
+    pub fn min_arity(&self) -> usize {
+        match self {
+            HyInstrOp::MGetElementPtr => 2, // base ptr + one index
+            HyInstrOp::Invoke => 1,         // function ptr
+            HyInstrOp::Phi => 1,            // at least one incoming value
+            HyInstrOp::InsertValue => 2,    // aggregate + value
+            HyInstrOp::ExtractValue => 1,   // aggregate
+            _ => self.arity().unwrap_or(0),
+        }
+    }
+
     /// Return true when the instruction carries an additional variant field.
     pub fn has_variant(&self) -> bool {
         matches!(
@@ -345,6 +373,41 @@ impl HyInstrOp {
                 | HyInstrOp::IRem
         )
     }
+
+    /// Return true when the operands of the instruction can be swapped without
+    /// changing its result.
+    pub fn is_commutative(&self) -> bool {
+        matches!(
+            self,
+            HyInstrOp::IAdd
+                | HyInstrOp::IMul
+                | HyInstrOp::IAnd
+                | HyInstrOp::IOr
+                | HyInstrOp::IXor
+                | HyInstrOp::IEquiv
+                | HyInstrOp::FAdd
+                | HyInstrOp::FMul
+        )
+    }
+
+    /// Return true when chains of the instruction can be regrouped without changing
+    /// their result, i.e. `(a op b) op c == a op (b op c)`.
+    ///
+    /// Floating-point operations are never associative under strict semantics as
+    /// each intermediate result is rounded. For `iadd` and `imul` this only holds
+    /// with the [`int::OverflowSignednessPolicy::Wrap`] policy; saturating and
+    /// trapping policies depend on the intermediate results.
+    pub fn is_associative(&self) -> bool {
+        matches!(
+            self,
+            HyInstrOp::IAdd
+                | HyInstrOp::IMul
+                | HyInstrOp::IAnd
+                | HyInstrOp::IOr
+                | HyInstrOp::IXor
+                | HyInstrOp::IEquiv
+        )
+    }
 }
 
 impl std::str::FromStr for HyInstrOp {
@@ -355,11 +418,80 @@ impl std::str::FromStr for HyInstrOp {
     }
 }
 
+/// Identity of an instruction up to its destination name and the order of the operands
+/// of commutative operations, see [`HyInstr::canonical_key`].
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct CanonicalKey(HyInstr);
+
 impl HyInstr {
+    /// Return true when both operands of the instruction can be swapped without
+    /// changing its result.
+    ///
+    /// Unlike [`HyInstrOp::is_commutative`], this also accounts for comparisons with a
+    /// symmetric predicate (e.g., `icmp.eq` or `fcmp.une`).
+    pub fn has_commutative_operands(&self) -> bool {
+        match self {
+            HyInstr::ICmp(icmp) => {
+                matches!(icmp.variant, int::ICmpVariant::Eq | int::ICmpVariant::Ne)
+            }
+            HyInstr::FCmp(fcmp) => matches!(
+                fcmp.variant,
+                fp::FCmpVariant::Oeq
+                    | fp::FCmpVariant::One
+                    | fp::FCmpVariant::Ueq
+                    | fp::FCmpVariant::Une
+                    | fp::FCmpVariant::Ord
+            ),
+            _ => self.op().is_commutative(),
+        }
+    }
+
+    /// Build a key identifying the value computed by this instruction.
+    ///
+    /// The destination name is erased, and the operands of instructions for which
+    /// [`HyInstr::has_commutative_operands`] holds are sorted according to the `Ord`
+    /// of [`Operand`]. Hence `iadd %a, %b` and `iadd %b, %a` share the same key, while
+    /// `isub %a, %b` and `isub %b, %a` do not.
+    /// Keys only make sense when compared within the same function.
+    pub fn canonical_key(&self) -> CanonicalKey {
+        let mut instr = self.clone();
+        if instr.destination().is_some() {
+            instr.set_destination(Name(0));
+        }
+
+        if instr.has_commutative_operands() {
+            let mut operands: Vec<&mut Operand> = instr.operands_mut().collect();
+            if let [lhs, rhs] = operands.as_mut_slice()
+                && rhs < lhs
+            {
+                std::mem::swap(&mut **lhs, &mut **rhs);
+            }
+        }
+
+        CanonicalKey(instr)
+    }
+
     /// Return the discriminant for this instruction value.
     pub fn op(&self) -> HyInstrOp {
         self.into()
     }
+
+    /// Return the bit width of the value produced by this instruction.
+    ///
+    /// Returns `None` when the instruction produces no value, or when the width of
+    /// its result is target-dependent or unknown (pointers, wildcards, aggregates, ...).
+    /// See [`PrimaryType::size_bits`](crate::types::primary::PrimaryType::size_bits) for details.
+    pub fn result_bit_width(&self, registry: &TypeRegistry) -> Option<u32> {
+        let ty = self.destination_type()?;
+        if ty.is_wildcard() {
+            return None;
+        }
+
+        match &*registry.get(ty)? {
+            AnyType::Primary(primary) => primary.size_bits(),
+            _ => None,
+        }
+    }
 }
 
 macro_rules! define_instr_any_instr {
@@ -14,20 +14,26 @@
 //! tagged union of all concrete instruction forms.
 use std::{
     borrow::Cow,
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap, hash_map::Entry},
+    hash::{Hash, Hasher},
+    ops::Range,
     sync::Arc,
 };
 
 use crate::{
+    analysis::{AnalysisStatistic, TerminationScope},
     consts::AnyConst,
     modules::{
-        instructions::{HyInstr, Instruction},
+        instructions::{CanonicalKey, HyInstr, Instruction, InstructionFlags},
         operand::{Label, Name, Operand},
         symbol::{ExternalFunction, FunctionPointer, FunctionPointerType},
-        terminator::Trap,
+        terminator::{Jump, Trap},
     },
-    types::{TypeRegistry, Typeref, primary::WType},
-    utils::Error,
+    types::{
+        AnyType, TypeRegistry, Typeref,
+        primary::{PrimaryBasicType, PrimaryType, VcType, WType},
+    },
+    utils::{Error, StableHasher},
 };
 use petgraph::prelude::DiGraphMap;
 #[cfg(feature = "serde")]
@@ -283,7 +289,8 @@ impl std::str::FromStr for CallingConvention {
 ///
 /// This structure identifies an instruction by the basic block label it resides in
 /// and the index of the instruction within that block.
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(
     feature = "borsh",
     derive(borsh::BorshSerialize, borsh::BorshDeserialize)
@@ -377,7 +384,7 @@ impl BasicBlock {
 /// and analyzable. Regular functions, on the other hand, represent executable code and may
 /// contain arbitrary control flow, including loops and recursion.
 ///
-#[derive(Debug, Clone, Hash)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(
     feature = "borsh",
@@ -404,6 +411,42 @@ pub struct Function {
     pub meta_function: bool,
     /// If this function was derived from another, holds the source function UUID.
     pub derived_from: Option<Uuid>,
+    /// Byte ranges in the source text of each instruction (populated by the parser).
+    ///
+    /// Keys always have their `reserved` field set to zero. Transformations that move
+    /// or remove instructions keep this map in sync, see [`Function::retain_instructions`].
+    pub spans: BTreeMap<InstructionRef, Range<usize>>,
+}
+
+impl Hash for Function {
+    /// Hash every field except [`Function::spans`], such that source positions (and
+    /// hence the formatting of the parsed text) do not affect the hash.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let Self {
+            uuid,
+            name,
+            params,
+            return_type,
+            body,
+            visibility,
+            cconv,
+            wildcard_types,
+            meta_function,
+            derived_from,
+            spans: _,
+        } = self;
+
+        uuid.hash(state);
+        name.hash(state);
+        params.hash(state);
+        return_type.hash(state);
+        body.hash(state);
+        visibility.hash(state);
+        cconv.hash(state);
+        wildcard_types.hash(state);
+        meta_function.hash(state);
+        derived_from.hash(state);
+    }
 }
 
 impl Default for Function {
@@ -428,6 +471,7 @@ impl Default for Function {
             wildcard_types: Default::default(),
             meta_function: false,
             derived_from: Default::default(),
+            spans: Default::default(),
         }
     }
 }
@@ -593,44 +637,175 @@ impl Function {
         Ok(())
     }
 
+    /// Verify that the definition of every SSA name dominates its uses.
+    ///
+    /// A name used by a phi instruction must be available at the end of the matching
+    /// predecessor block instead. Uses within unreachable blocks are not checked.
+    fn verify_use_before_def(&self) -> Result<(), Error> {
+        let flow = self.derive_function_flow();
+        let dominators = petgraph::algo::dominators::simple_fast(&flow, Label::NIL);
+        let dest_map = self.derive_dest_map();
+
+        // Check that `name` is available before the instruction at `index` of `block`
+        let check_use = |name: Name, block: Label, index: usize| -> Result<(), Error> {
+            let Some(mut block_dominators) = dominators.dominators(block) else {
+                return Ok(()); // unreachable block
+            };
+            // Function parameters are not in the dest-map and available everywhere
+            let Some(def) = dest_map.get(&name) else {
+                return Ok(());
+            };
+
+            let available = if def.block == block {
+                (def.index as usize) < index
+            } else {
+                block_dominators.any(|dominator| dominator == def.block)
+            };
+
+            if available {
+                Ok(())
+            } else {
+                Err(Error::UseBeforeDef { name, block })
+            }
+        };
+
+        for (label, bb) in &self.body {
+            for (index, instr) in bb.instructions.iter().enumerate() {
+                if let Some(phi) = instr.try_as_phi_ref() {
+                    for (operand, pred) in &phi.values {
+                        if let Operand::Reg(name) = operand
+                            && let Some(pred_bb) = self.body.get(pred)
+                        {
+                            check_use(*name, *pred, pred_bb.instructions.len())?;
+                        }
+                    }
+                } else {
+                    for name in instr.dependencies() {
+                        check_use(name, *label, index)?;
+                    }
+                }
+            }
+
+            for name in bb.terminator.dependencies() {
+                check_use(name, *label, bb.instructions.len())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify_instruction_arity(&self) -> Result<(), Error> {
+        for instr in self.body.values().flat_map(|bb| bb.instructions.iter()) {
+            let op = instr.op();
+            let found = instr.operands().count();
+            let sound = match op.arity() {
+                Some(arity) => found == arity,
+                None => found >= op.min_arity(),
+            };
+
+            if !sound {
+                return Err(Error::ArityMismatch {
+                    op: op.opname().to_string(),
+                    expected: op.min_arity(),
+                    found,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     fn verify_ssa_soundness(&self) -> Result<(), Error> {
+        let mut errors = vec![];
+        self.collect_ssa_errors(&mut errors);
+        errors.into_iter().next().map_or(Ok(()), Err)
+    }
+
+    /// Collect every SSA violation of the function (each offending name is reported once).
+    fn collect_ssa_errors(&self, errors: &mut Vec<Error>) {
         let mut defined_names = BTreeSet::new();
 
         // 1. Construct defined_names
-        for (name, _) in self.params.iter() {
-            if !defined_names.insert(*name) {
-                return Err(Error::DuplicateSSAName { duplicate: *name });
+        let destinations = self.body.values().flat_map(|bb| {
+            bb.instructions
+                .iter()
+                .filter_map(|instr| instr.destination())
+        });
+        for name in self
+            .params
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(destinations)
+        {
+            if !defined_names.insert(name) {
+                errors.push(Error::DuplicateSSAName { duplicate: name });
             }
         }
 
+        // 2. Ensure all operands refer to defined names
+        let mut reported = BTreeSet::new();
         for bb in self.body.values() {
-            for instr in &bb.instructions {
-                if let Some(dest) = instr.destination()
-                    && !defined_names.insert(dest)
-                {
-                    return Err(Error::DuplicateSSAName { duplicate: dest });
+            let used_names = bb
+                .instructions
+                .iter()
+                .flat_map(|instr| instr.dependencies())
+                .chain(bb.terminator.dependencies());
+
+            for name in used_names {
+                if !defined_names.contains(&name) && reported.insert(name) {
+                    errors.push(Error::UndefinedSSAName { undefined: name });
                 }
             }
         }
+    }
 
-        // 2. Ensure all operands refer to defined names
-        for bb in self.body.values() {
-            for instr in &bb.instructions {
-                for name in instr.dependencies() {
-                    if !defined_names.contains(&name) {
-                        return Err(Error::UndefinedSSAName { undefined: name });
-                    }
-                }
+    fn collect_phi_predecessor_errors(&self, errors: &mut Vec<Error>) {
+        let mut predecessors: BTreeMap<Label, BTreeSet<Label>> = BTreeMap::new();
+        for (label, bb) in &self.body {
+            for (target, _) in bb.terminator.iter_targets() {
+                predecessors.entry(target).or_default().insert(*label);
             }
+        }
 
-            for name in bb.terminator.dependencies() {
-                if !defined_names.contains(&name) {
-                    return Err(Error::UndefinedSSAName { undefined: name });
+        for (label, bb) in &self.body {
+            let expected = predecessors.remove(label).unwrap_or_default();
+            for phi in bb.instructions.iter().filter_map(|i| i.try_as_phi_ref()) {
+                let found: BTreeSet<Label> = phi.values.iter().map(|(_, l)| *l).collect();
+                if found != expected {
+                    errors.push(Error::PhiPredecessorMismatch {
+                        function: self.name.clone().unwrap_or_else(|| self.uuid.to_string()),
+                        block: *label,
+                        missing: expected.difference(&found).copied().collect(),
+                        unexpected: found.difference(&expected).copied().collect(),
+                    });
                 }
             }
         }
+    }
 
-        Ok(())
+    /// Labels of the blocks reachable from the entry block (empty if there is none).
+    fn reachable_blocks(&self) -> BTreeSet<Label> {
+        let flow = self.derive_function_flow();
+        if !flow.contains_node(Label::NIL) {
+            return BTreeSet::new();
+        }
+
+        let mut reachable = BTreeSet::new();
+        let mut dfs = petgraph::visit::Dfs::new(&flow, Label::NIL);
+        while let Some(label) = dfs.next(&flow) {
+            reachable.insert(label);
+        }
+        reachable
+    }
+
+    fn collect_reachability_errors(&self, errors: &mut Vec<Error>) {
+        let reachable = self.reachable_blocks();
+        for label in self.body.keys().filter(|label| !reachable.contains(label)) {
+            errors.push(Error::UnreachableBlock {
+                function: self.name.clone().unwrap_or_else(|| self.uuid.to_string()),
+                label: *label,
+            });
+        }
     }
 
     /// Check whether the function should be treated as a meta-function.
@@ -660,6 +835,83 @@ impl Function {
         std::mem::swap(&mut self.wildcard_types, &mut placeholder);
     }
 
+    /// Remove all meta-instructions (assertions, assumptions, probabilities, ...) from
+    /// the function, returning the number of removed instructions.
+    ///
+    /// This is typically used before lowering a function to executable code. The
+    /// `meta_function` flag is left untouched and wildcard types are regenerated.
+    ///
+    /// # Errors
+    /// Returns [`Error::MetaResultInUse`] (leaving the function unchanged) if a regular
+    /// instruction or a terminator depends on the result of a meta-instruction.
+    pub fn strip_meta(&mut self) -> Result<usize, Error> {
+        let meta_names: BTreeSet<Name> = self
+            .iter()
+            .filter(|(instr, _)| instr.is_meta_instruction())
+            .filter_map(|(instr, _)| instr.destination())
+            .collect();
+
+        for bb in self.body.values() {
+            let used_names = bb
+                .instructions
+                .iter()
+                .filter(|instr| !instr.is_meta_instruction())
+                .flat_map(|instr| instr.dependencies())
+                .chain(bb.terminator.dependencies());
+
+            for name in used_names {
+                if meta_names.contains(&name) {
+                    return Err(Error::MetaResultInUse {
+                        function: self.name.clone().unwrap_or_else(|| self.uuid.to_string()),
+                        name,
+                    });
+                }
+            }
+        }
+
+        let removed = self.retain_instructions(|_, instr| !instr.is_meta_instruction());
+        self.generate_wildcards();
+        debug_assert!(self.verify_no_meta_instruction().is_ok());
+        Ok(removed)
+    }
+
+    /// Retain only the instructions for which `predicate` returns `true`, returning the
+    /// number of removed instructions.
+    ///
+    /// The predicate receives the reference of each instruction *before* any removal.
+    /// Source spans are shifted to follow the instructions that remain in place.
+    pub fn retain_instructions<F>(&mut self, mut predicate: F) -> usize
+    where
+        F: FnMut(InstructionRef, &HyInstr) -> bool,
+    {
+        let mut removed = 0;
+        let mut spans = BTreeMap::new();
+
+        for (label, bb) in self.body.iter_mut() {
+            let mut index = 0;
+            let mut kept = 0;
+
+            bb.instructions.retain(|instr| {
+                let reference = InstructionRef::from((*label, index));
+                index += 1;
+
+                let keep = predicate(reference, instr);
+                if keep {
+                    if let Some(span) = self.spans.remove(&reference) {
+                        spans.insert(InstructionRef::from((*label, kept)), span);
+                    }
+                    kept += 1;
+                }
+                keep
+            });
+
+            removed += index - kept;
+        }
+
+        self.spans = spans;
+        removed
+    }
+
     /// Returns whether the function is incomplete (i.e., has unresolved wildcard types).
     pub fn is_incomplete(&self) -> bool {
         !self.wildcard_types.is_empty()
@@ -703,7 +955,9 @@ impl Function {
     /// - No meta-instructions are present in non-meta functions.
     /// - Phi instructions are the first instructions in their respective blocks.
     /// - Target basic blocks referenced by terminators exist.
-    /// - SSA form is maintained (all names are defined before use).
+    /// - SSA form is maintained (all names are defined exactly once and definitions
+    ///   dominate their uses).
+    /// - Instructions have the operand count expected by their opcode.
     /// - Size constraints for blocks and functions are respected.
     /// - The existence of an entry block.
     ///
@@ -715,6 +969,7 @@ impl Function {
         self.verify_phi_first_instr_of_block()?;
         self.verify_target_soundness()?;
         self.verify_ssa_soundness()?;
+        self.verify_instruction_arity()?;
         self.verify_size_constraints()?;
 
         // Ensure existence of entry block
@@ -722,8 +977,49 @@ impl Function {
             return Err(Error::MissingEntryBlock);
         }
 
-        // TODO: Verify that all SSA names are defined before use (topological order)
-        Ok(())
+        self.verify_use_before_def()
+    }
+
+    /// Verify the structure of a function under construction, reporting every error
+    /// found instead of stopping at the first one.
+    ///
+    /// This is tailored for programmatically built functions and checks that:
+    /// - The entry block exists and every block is reachable from it.
+    /// - Terminators only target existing blocks.
+    /// - Phi instructions come first and have exactly one incoming value per predecessor.
+    /// - SSA names are defined exactly once and every used name is defined.
+    ///
+    pub fn verify_construction(&self) -> Result<(), Vec<Error>> {
+        let mut errors = vec![];
+
+        if !self.body.contains_key(&Label::NIL) {
+            errors.push(Error::MissingEntryBlock);
+        } else {
+            self.collect_reachability_errors(&mut errors);
+        }
+
+        for bb in self.body.values() {
+            for (target_label, _) in bb.terminator.iter_targets() {
+                if !self.body.contains_key(&target_label) {
+                    errors.push(Error::UndefinedBasicBlock {
+                        function: self.name.clone().unwrap_or_else(|| self.uuid.to_string()),
+                        label: target_label,
+                    });
+                }
+            }
+        }
+
+        if let Err(err) = self.verify_phi_first_instr_of_block() {
+            errors.push(err);
+        }
+        self.collect_phi_predecessor_errors(&mut errors);
+        self.collect_ssa_errors(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
     /// Perform type checking on the function.
@@ -783,6 +1079,442 @@ impl Function {
         }
     }
 
+    /// Find the SSA renaming that turns this function into `other`, if any.
+    ///
+    /// Two functions are equal up to SSA renaming when they have the same blocks, the
+    /// same parameter types and the same instructions and terminators once every
+    /// name of `self` is replaced by its image. Parameters and destinations are paired
+    /// in definition order, hence the renaming is a bijection between defined names.
+    ///
+    /// Returns `None` if no such renaming exists.
+    pub fn ssa_renaming_to(&self, other: &Function) -> Option<BTreeMap<Name, Name>> {
+        fn definitions(func: &Function) -> impl Iterator<Item = Name> + '_ {
+            func.params.iter().map(|(name, _)| *name).chain(
+                func.body
+                    .values()
+                    .flat_map(|bb| bb.instructions.iter().filter_map(|i| i.destination())),
+            )
+        }
+
+        if !self.body.keys().eq(other.body.keys())
+            || definitions(self).count() != definitions(other).count()
+        {
+            return None;
+        }
+
+        let mut renaming = BTreeMap::new();
+        let mut image = BTreeSet::new();
+        for (from, to) in definitions(self).zip(definitions(other)) {
+            if renaming.insert(from, to).is_some() || !image.insert(to) {
+                return None;
+            }
+        }
+
+        let rename = |name: Name| renaming.get(&name).copied();
+        let renamed_params = self.params.iter().map(|(name, ty)| (renaming[name], *ty));
+        if !renamed_params.eq(other.params.iter().copied()) {
+            return None;
+        }
+
+        for (bb, other_bb) in self.body.values().zip(other.body.values()) {
+            if bb.instructions.len() != other_bb.instructions.len() {
+                return None;
+            }
+
+            for (instr, other_instr) in bb.instructions.iter().zip(&other_bb.instructions) {
+                let mut instr = instr.clone();
+                if let Some(dest) = instr.destination() {
+                    instr.set_destination(renaming[&dest]);
+                }
+                instr.remap_operands(rename);
+                if instr != *other_instr {
+                    return None;
+                }
+            }
+
+            let mut terminator = bb.terminator.clone();
+            terminator.remap_operands(rename);
+            if terminator != other_bb.terminator {
+                return None;
+            }
+        }
+
+        Some(renaming)
+    }
+
+    /// Replace every use of the SSA name `name` with `replacement` across all
+    /// instructions and terminators of the function.
+    ///
+    /// The definition of `name` (if any) is left untouched; callers are expected to
+    /// remove it afterwards if it became dead.
+    pub fn substitute_operand(&mut self, name: Name, replacement: Operand) {
+        for bb in self.body.values_mut() {
+            for instr in bb.instructions.iter_mut() {
+                instr.substitute_operand(name, replacement.clone());
+            }
+
+            bb.terminator.substitute_operand(name, replacement.clone());
+        }
+    }
+
+    /// Redirect every control-flow edge (and label reference held by analysis
+    /// meta-instructions) targeting `from` so that it targets `to` instead.
+    ///
+    /// Phi predecessor labels are left untouched, callers are responsible for
+    /// updating them as the right behavior depends on the transformation.
+    fn redirect_label(&mut self, from: Label, to: Label) {
+        for bb in self.body.values_mut() {
+            for target in bb.terminator.iter_targets_mut() {
+                if *target == from {
+                    *target = to;
+                }
+            }
+
+            for instr in bb.instructions.iter_mut() {
+                if let HyInstr::MetaAnalysisStat(stat) = instr
+                    && let AnalysisStatistic::TerminationBehavior(TerminationScope::ReachAny(
+                        labels,
+                    )) = &mut stat.statistic
+                {
+                    for label in labels.iter_mut().filter(|label| **label == from) {
+                        *label = to;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check whether every phi fed by `duplicate` receives the same value from
+    /// `representative`, making it safe to fold `duplicate` into `representative`.
+    fn phi_incomings_agree(&self, representative: Label, duplicate: Label) -> bool {
+        self.body
+            .values()
+            .flat_map(|bb| bb.instructions.iter())
+            .filter_map(|instr| instr.try_as_phi_ref())
+            .all(|phi| {
+                phi.values
+                    .iter()
+                    .filter(|(_, label)| *label == duplicate)
+                    .all(|(value, _)| phi.values.contains(&(value.clone(), representative)))
+            })
+    }
+
+    /// Merge structurally identical basic blocks.
+    ///
+    /// Two blocks are considered identical when they hold the same instructions and
+    /// the same terminator (labels are ignored). Every edge to a duplicate is redirected
+    /// to a single representative (the block with the smallest label) and the duplicate
+    /// is removed. Phi instructions drop the incoming entries of removed blocks.
+    ///
+    /// Blocks defining SSA names are never merged (they cannot be identical in a valid
+    /// function), nor are blocks whose successors' phis would receive different values
+    /// from the duplicate and the representative. The process is repeated until a fixed
+    /// point is reached, as merging may expose new duplicates.
+    ///
+    /// Returns the number of removed blocks.
+    pub fn merge_identical_blocks(&mut self) -> usize {
+        let mut removed = 0;
+
+        loop {
+            let mut seen: HashMap<(&[HyInstr], &terminator::HyTerminator), Label> = HashMap::new();
+            let mut candidate = None;
+
+            for (label, bb) in &self.body {
+                if bb.instructions.iter().any(|i| i.destination().is_some()) {
+                    continue;
+                }
+
+                match seen.entry((bb.instructions.as_slice(), &bb.terminator)) {
+                    Entry::Occupied(entry) => {
+                        if self.phi_incomings_agree(*entry.get(), *label) {
+                            candidate = Some((*entry.get(), *label));
+                            break;
+                        }
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(*label);
+                    }
+                }
+            }
+
+            let Some((representative, duplicate)) = candidate else {
+                break;
+            };
+
+            self.body.remove(&duplicate);
+            self.spans
+                .retain(|reference, _| reference.block != duplicate);
+            self.redirect_label(duplicate, representative);
+            for bb in self.body.values_mut() {
+                for instr in bb.instructions.iter_mut() {
+                    if let HyInstr::Phi(phi) = instr {
+                        phi.values.retain(|(_, label)| *label != duplicate);
+                    }
+                }
+            }
+
+            removed += 1;
+        }
+
+        removed
+    }
+
+    /// Remove the basic block `label` from the function and return it.
+    ///
+    /// The entry block cannot be removed, nor can a block still targeted by the
+    /// terminator of another block (redirect those edges first). Phi instructions drop their incoming entries
+    /// from the removed block, and the spans of its instructions are discarded.
+    pub fn remove_block(&mut self, label: Label) -> Result<BasicBlock, Error> {
+        let function = || self.name.clone().unwrap_or_else(|| self.uuid.to_string());
+
+        if label == Label::NIL {
+            return Err(Error::EntryBlockRemoval {
+                function: function(),
+            });
+        }
+        if !self.body.contains_key(&label) {
+            return Err(Error::UndefinedBasicBlock {
+                function: function(),
+                label,
+            });
+        }
+        if let Some((predecessor, _)) = self.body.iter().find(|(predecessor, bb)| {
+            **predecessor != label && bb.terminator.iter_targets().any(|(t, _)| t == label)
+        }) {
+            return Err(Error::BlockStillReferenced {
+                function: function(),
+                label,
+                predecessor: *predecessor,
+            });
+        }
+
+        let block = self.body.remove(&label).unwrap();
+        self.spans.retain(|reference, _| reference.block != label);
+        for bb in self.body.values_mut() {
+            for instr in bb.instructions.iter_mut() {
+                if let HyInstr::Phi(phi) = instr {
+                    phi.values.retain(|(_, incoming)| *incoming != label);
+                }
+            }
+        }
+
+        Ok(block)
+    }
+
+    /// Remove every basic block that cannot be reached from the entry block.
+    ///
+    /// Phi instructions drop their incoming entries from removed blocks and the spans
+    /// of removed instructions are discarded. This is typically run after folding
+    /// branches. A function without entry block is left untouched.
+    ///
+    /// Returns the number of removed blocks.
+    pub fn remove_unreachable_blocks(&mut self) -> usize {
+        if !self.body.contains_key(&Label::NIL) {
+            return 0;
+        }
+
+        let reachable = self.reachable_blocks();
+        let before = self.body.len();
+        self.body.retain(|label, _| reachable.contains(label));
+        self.spans
+            .retain(|reference, _| reachable.contains(&reference.block));
+        for bb in self.body.values_mut() {
+            for instr in bb.instructions.iter_mut() {
+                if let HyInstr::Phi(phi) = instr {
+                    phi.values.retain(|(_, label)| reachable.contains(label));
+                }
+            }
+        }
+
+        before - self.body.len()
+    }
+
+    /// Merge instructions recomputing a value that is already available.
+    ///
+    /// An instruction is redundant when a simple (see [`InstructionFlags::SIMPLE`])
+    /// instruction with the same [`HyInstr::canonical_key`] dominates it, see
+    /// [`Function::derive_dominator_tree`]. Redundant instructions are removed and their
+    /// uses are rewritten to the dominating destination. Meta-instructions are never
+    /// merged, and blocks unreachable from the entry block are not considered.
+    ///
+    /// Returns the number of removed instructions.
+    pub fn eliminate_common_subexpressions(&mut self) -> usize {
+        let idom = self.derive_dominator_tree();
+        let mut children: BTreeMap<Label, Vec<Label>> = BTreeMap::new();
+        for (label, parent) in &idom {
+            if label != parent {
+                children.entry(*parent).or_default().push(*label);
+            }
+        }
+
+        // Visit the dominator tree in pre-order so that dominating values are known first
+        let mut available: BTreeMap<Label, HashMap<CanonicalKey, Name>> = BTreeMap::new();
+        let mut replacements: BTreeMap<Name, Name> = BTreeMap::new();
+        let mut redundant: BTreeSet<InstructionRef> = BTreeSet::new();
+        let mut stack: Vec<Label> = idom
+            .contains_key(&Label::NIL)
+            .then_some(Label::NIL)
+            .into_iter()
+            .collect();
+
+        while let Some(label) = stack.pop() {
+            stack.extend(children.get(&label).into_iter().flatten().rev());
+            let mut local: HashMap<CanonicalKey, Name> = HashMap::new();
+
+            for (index, instr) in self
+                .body
+                .get_mut(&label)
+                .unwrap()
+                .instructions
+                .iter_mut()
+                .enumerate()
+            {
+                for operand in instr.operands_mut() {
+                    if let Operand::Reg(name) = operand
+                        && let Some(replacement) = replacements.get(name)
+                    {
+                        *name = *replacement;
+                    }
+                }
+
+                let Some(dest) = instr.destination() else {
+                    continue;
+                };
+                if !instr.is_simple() || instr.is_meta_instruction() {
+                    continue;
+                }
+
+                let key = instr.canonical_key();
+                let mut dominator = label;
+                let existing = loop {
+                    if dominator == label {
+                        if let Some(name) = local.get(&key) {
+                            break Some(*name);
+                        }
+                    } else if let Some(name) = available[&dominator].get(&key) {
+                        break Some(*name);
+                    }
+
+                    if idom[&dominator] == dominator {
+                        break None;
+                    }
+                    dominator = idom[&dominator];
+                };
+
+                match existing {
+                    Some(name) => {
+                        replacements.insert(dest, name);
+                        redundant.insert(InstructionRef::from((label, index)));
+                    }
+                    None => {
+                        local.insert(key, dest);
+                    }
+                }
+            }
+
+            available.insert(label, local);
+        }
+
+        // Uses in phis (and in later blocks) may not have been visited yet
+        for (from, to) in replacements {
+            self.substitute_operand(from, Operand::Reg(to));
+        }
+        self.retain_instructions(|reference, _| !redundant.contains(&reference))
+    }
+
+    /// Split every critical edge of the control-flow graph.
+    ///
+    /// An edge is critical when its source has several successors and its destination
+    /// several predecessors. Each such edge is split by inserting a fresh empty block
+    /// that jumps to the original destination. Phi instructions of the destination are
+    /// updated to reference the new block instead of the original source.
+    ///
+    /// Returns the number of split edges.
+    pub fn split_critical_edges(&mut self) -> usize {
+        let successors: BTreeMap<Label, BTreeSet<Label>> = self
+            .body
+            .iter()
+            .map(|(label, bb)| {
+                (
+                    *label,
+                    bb.terminator
+                        .iter_targets()
+                        .map(|(target, _)| target)
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let mut predecessor_count: BTreeMap<Label, usize> = BTreeMap::new();
+        for target in successors.values().flatten() {
+            *predecessor_count.entry(*target).or_default() += 1;
+        }
+
+        let critical_edges: Vec<(Label, Label)> = successors
+            .iter()
+            .filter(|(_, targets)| targets.len() > 1)
+            .flat_map(|(source, targets)| {
+                targets
+                    .iter()
+                    .filter(|target| predecessor_count[target] > 1)
+                    .map(|target| (*source, *target))
+            })
+            .collect();
+
+        for &(source, destination) in &critical_edges {
+            let split = self.next_available_label();
+            self.body.insert(
+                split,
+                BasicBlock {
+                    label: split,
+                    instructions: vec![],
+                    terminator: Jump {
+                        target: destination,
+                    }
+                    .into(),
+                },
+            );
+
+            for target in self
+                .body
+                .get_mut(&source)
+                .unwrap()
+                .terminator
+                .iter_targets_mut()
+            {
+                if *target == destination {
+                    *target = split;
+                }
+            }
+
+            if let Some(bb) = self.body.get_mut(&destination) {
+                for phi in bb
+                    .instructions
+                    .iter_mut()
+                    .filter_map(|i| i.try_as_phi_mut())
+                {
+                    for (_, label) in phi.values.iter_mut().filter(|(_, l)| *l == source) {
+                        *label = split;
+                    }
+                }
+            }
+        }
+
+        critical_edges.len()
+    }
+
+    /// Retrieve the source byte range of the instruction at `reference`, if known.
+    ///
+    /// Spans are only recorded for functions built by the parser.
+    pub fn span_of(&self, reference: InstructionRef) -> Option<Range<usize>> {
+        self.spans
+            .get(&InstructionRef {
+                reserved: 0,
+                ..reference
+            })
+            .cloned()
+    }
+
     /// Retrieve instruction from a [`InstructionRef`].
     ///
     /// Returns `None` if the block or instruction index is invalid.
@@ -793,6 +1525,60 @@ impl Function {
             .and_then(|bb| bb.instructions.get(reference.index as usize))
     }
 
+    /// Returns the internal functions called by this function, or `None` if the function
+    /// has side effects of its own (memory operations, calls to external functions or
+    /// indirect calls).
+    fn direct_pure_callees(&self) -> Option<BTreeSet<Uuid>> {
+        let mut callees = BTreeSet::new();
+        for (instr, _) in self.iter() {
+            if instr.flags().contains(InstructionFlags::MEMORY) {
+                return None;
+            }
+
+            if let HyInstr::Invoke(invoke) = instr {
+                match &invoke.function {
+                    Operand::Imm(AnyConst::FuncPtr(FunctionPointer::Internal(uuid))) => {
+                        callees.insert(*uuid);
+                    }
+                    _ => return None,
+                }
+            }
+        }
+
+        Some(callees)
+    }
+
+    /// Returns whether the function is free of observable side effects.
+    ///
+    /// A function is pure when it performs no memory operation (loads and stores,
+    /// volatile or not) and only calls internal functions of `module` that are pure
+    /// themselves. Calls to external functions, indirect calls and calls to functions
+    /// missing from `module` are considered impure. Recursive calls are handled by
+    /// exploring the call graph reachable from this function only once per function.
+    pub fn is_pure(&self, module: &Module) -> bool {
+        let mut visited = BTreeSet::from([self.uuid]);
+        let mut stack = vec![self];
+
+        while let Some(func) = stack.pop() {
+            let Some(callees) = func.direct_pure_callees() else {
+                return false;
+            };
+
+            for callee in callees {
+                if !visited.insert(callee) {
+                    continue;
+                }
+
+                match module.get_internal_function_by_uuid(callee) {
+                    Some(callee) => stack.push(callee),
+                    None => return false,
+                }
+            }
+        }
+
+        true
+    }
+
     /// Analyzes the control flow of a function and constructs its control flow graph (CFG).
     ///
     /// The CFG is represented as a directed graph where nodes are basic block labels
@@ -821,6 +1607,166 @@ impl Function {
         graph
     }
 
+    /// Derive the successor map of the function.
+    ///
+    /// For each basic block, lists the labels targeted by its terminator in terminator
+    /// order (e.g., `[target_true, target_false]` for branches). This is a lightweight
+    /// alternative to [`Function::derive_function_flow`] when the full graph is not needed.
+    ///
+    pub fn derive_successors(&self) -> BTreeMap<Label, Vec<Label>> {
+        self.body
+            .iter()
+            .map(|(label, block)| {
+                (
+                    *label,
+                    block
+                        .terminator
+                        .iter_targets()
+                        .map(|(target, _)| target)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Derive the immediate dominator of each basic block.
+    ///
+    /// Dominators are computed with the Cooper-Harvey-Kennedy iterative algorithm
+    /// over [`Function::derive_function_flow`], rooted at the entry block. By
+    /// convention the entry block maps to itself. Blocks unreachable from the entry
+    /// block are missing from the returned map.
+    ///
+    pub fn derive_dominator_tree(&self) -> BTreeMap<Label, Label> {
+        let flow = self.derive_function_flow();
+        if !flow.contains_node(Label::NIL) {
+            return BTreeMap::new();
+        }
+
+        let dominators = petgraph::algo::dominators::simple_fast(&flow, Label::NIL);
+        self.body
+            .keys()
+            .filter_map(|label| {
+                if *label == Label::NIL {
+                    Some((Label::NIL, Label::NIL))
+                } else {
+                    dominators
+                        .immediate_dominator(*label)
+                        .map(|idom| (*label, idom))
+                }
+            })
+            .collect()
+    }
+
+    /// Derive the natural loops of the function.
+    ///
+    /// A back-edge is an edge whose target (the loop header) dominates its source, see
+    /// [`Function::derive_dominator_tree`]. Each back-edge yields one loop, whose body
+    /// holds the header and every block reaching the source of the back-edge without
+    /// going through the header. Loops sharing a header are therefore reported
+    /// separately. Loops are sorted by back-edge.
+    ///
+    /// Cycles of an irreducible control-flow graph (entered through several blocks)
+    /// have no back-edge, hence are not reported.
+    ///
+    pub fn derive_loops(&self) -> Vec<NaturalLoop> {
+        let idoms = self.derive_dominator_tree();
+        let dominates = |dominator: Label, mut label: Label| loop {
+            if label == dominator {
+                return true;
+            }
+            match idoms.get(&label) {
+                Some(idom) if *idom != label => label = *idom,
+                _ => return false,
+            }
+        };
+
+        let mut predecessors: BTreeMap<Label, Vec<Label>> = BTreeMap::new();
+        for (label, bb) in &self.body {
+            for (target, _) in bb.terminator.iter_targets() {
+                predecessors.entry(target).or_default().push(*label);
+            }
+        }
+
+        let mut loops = vec![];
+        for (source, bb) in &self.body {
+            if !idoms.contains_key(source) {
+                continue; // unreachable block
+            }
+
+            // A terminator may target the same block several times (e.g. `branch %c, H, H`)
+            let successors: BTreeSet<Label> = bb
+                .terminator
+                .iter_targets()
+                .map(|(target, _)| target)
+                .collect();
+            for header in successors {
+                if !dominates(header, *source) {
+                    continue;
+                }
+
+                let mut body = BTreeSet::from([header]);
+                let mut stack = vec![*source];
+                while let Some(label) = stack.pop() {
+                    if body.insert(label) {
+                        // Unreachable predecessors are not part of the loop
+                        stack.extend(
+                            predecessors
+                                .get(&label)
+                                .into_iter()
+                                .flatten()
+                                .filter(|pred| idoms.contains_key(pred)),
+                        );
+                    }
+                }
+
+                loops.push(NaturalLoop {
+                    header,
+                    body,
+                    back_edge: (*source, header),
+                });
+            }
+        }
+
+        loops.sort_by_key(|natural_loop| natural_loop.back_edge);
+        loops
+    }
+
+    /// Derive the immediate post-dominator of each basic block.
+    ///
+    /// Post-dominators are computed as the dominators of the reversed CFG, rooted at a
+    /// virtual exit node to which every `ret`/`trap` block is connected. A block is
+    /// missing from the returned map when its immediate post-dominator is the virtual
+    /// exit (e.g., exit blocks themselves) or when no exit is reachable from it.
+    ///
+    pub fn derive_post_dominators(&self) -> BTreeMap<Label, Label> {
+        // `None` stands for the virtual exit node
+        let mut reversed: DiGraphMap<Option<Label>, ()> =
+            DiGraphMap::with_capacity(self.body.len() + 1, self.body.len() * 3);
+        reversed.add_node(None);
+
+        for (block_label, block) in &self.body {
+            reversed.add_node(Some(*block_label));
+            if block.terminator.is_ret() || block.terminator.is_trap() {
+                reversed.add_edge(None, Some(*block_label), ());
+            }
+
+            for (target_label, _) in block.terminator.iter_targets() {
+                reversed.add_edge(Some(target_label), Some(*block_label), ());
+            }
+        }
+
+        let dominators = petgraph::algo::dominators::simple_fast(&reversed, None);
+        self.body
+            .keys()
+            .filter_map(|label| {
+                dominators
+                    .immediate_dominator(Some(*label))
+                    .flatten()
+                    .map(|ipdom| (*label, ipdom))
+            })
+            .collect()
+    }
+
     /// Derive the dest-map, for each SSA name, find the instruction that defines it.
     ///
     /// You can use this to quickly lookup the instruction that defines a particular SSA name.
@@ -898,6 +1844,174 @@ impl Function {
         None
     }
 
+    /// Replace recursive calls by calls to the nil UUID, so that the body of the
+    /// function does not depend on its UUID.
+    fn erase_self_references(&mut self) {
+        let self_ptr = FunctionPointer::Internal(self.uuid);
+        self.remap_function_pointers(|ptr| {
+            (*ptr == self_ptr).then_some(FunctionPointer::Internal(Uuid::nil()))
+        });
+    }
+
+    /// Check whether two functions are equal up to SSA renaming.
+    ///
+    /// Both functions are compared after [`Function::normalize_ssa`], ignoring their
+    /// UUID, source spans and [`Function::derived_from`]. Recursive calls are considered
+    /// equal even though they reference different UUIDs. Types are compared by
+    /// [`Typeref`], hence both functions are expected to share the same registry.
+    pub fn structurally_equal(&self, other: &Function) -> bool {
+        let canonicalize = |function: &Function| {
+            let mut function = function.clone();
+            function.normalize_ssa();
+            function.erase_self_references();
+            function
+        };
+
+        let lhs = canonicalize(self);
+        let rhs = canonicalize(other);
+        lhs.name == rhs.name
+            && lhs.params == rhs.params
+            && lhs.return_type == rhs.return_type
+            && lhs.visibility == rhs.visibility
+            && lhs.cconv == rhs.cconv
+            && lhs.wildcard_types == rhs.wildcard_types
+            && lhs.meta_function == rhs.meta_function
+            && lhs
+                .body
+                .values()
+                .map(|bb| (bb.label, &bb.instructions, &bb.terminator))
+                .eq(rhs
+                    .body
+                    .values()
+                    .map(|bb| (bb.label, &bb.instructions, &bb.terminator)))
+    }
+
+    /// Collect the bit-widths of every integer type used by the function.
+    ///
+    /// This covers parameters, return and destination types, any other type referenced
+    /// by instructions (including the element type of integer vectors) and integer
+    /// immediates. Types missing from `registry` are ignored.
+    pub fn integer_widths(&self, registry: &TypeRegistry) -> BTreeSet<u32> {
+        let typerefs = self
+            .params
+            .iter()
+            .map(|(_, ty)| *ty)
+            .chain(self.return_type)
+            .chain(self.body.values().flat_map(|bb| {
+                bb.instructions
+                    .iter()
+                    .flat_map(|instr| instr.referenced_types())
+                    .chain(bb.terminator.referenced_types())
+            }))
+            .filter(|ty| !ty.is_wildcard())
+            .collect::<BTreeSet<_>>();
+
+        let mut widths = BTreeSet::new();
+        for ty in typerefs {
+            match registry.get(ty).as_deref() {
+                Some(AnyType::Primary(PrimaryType::Int(itype))) => {
+                    widths.insert(itype.num_bits());
+                }
+                Some(AnyType::Primary(PrimaryType::Vc(VcType {
+                    ty: PrimaryBasicType::Int(itype),
+                    ..
+                }))) => {
+                    widths.insert(itype.num_bits());
+                }
+                _ => {}
+            }
+        }
+
+        let operands = self.body.values().flat_map(|bb| {
+            bb.instructions
+                .iter()
+                .flat_map(|instr| instr.operands())
+                .chain(bb.terminator.operands())
+        });
+        for operand in operands {
+            if let Operand::Imm(AnyConst::Int(iconst)) = operand {
+                widths.insert(iconst.ty.num_bits());
+            }
+        }
+
+        widths
+    }
+
+    /// Compute a fingerprint of the function, e.g. to cache analysis results.
+    ///
+    /// The fingerprint is computed after SSA normalization (see [`Function::normalize_ssa`])
+    /// and with every type replaced by its structural fingerprint (see
+    /// [`TypeRegistry::fingerprint`]). It therefore does not depend on the SSA numbering,
+    /// on the function UUID nor on the registry the types come from. Source spans and
+    /// [`Function::derived_from`] are ignored as well. Recursive calls do not depend on
+    /// the function UUID either, and other functions referenced by `invoke`
+    /// instructions are hashed through their name when they are found in `module`.
+    /// Hence the same source parsed twice yields the same fingerprint.
+    ///
+    /// Callees missing from `module` (or unnamed) are hashed through their UUID, and
+    /// types missing from `registry` through their [`Typeref`]. Like
+    /// [`TypeRegistry::fingerprint`], the result is computed with [`StableHasher`] and
+    /// is stable across Rust releases and platforms, as long as the IR definitions
+    /// (instructions, constants and types) do not change.
+    pub fn fingerprint(&self, registry: &TypeRegistry, module: Option<&Module>) -> u64 {
+        let mut func = self.clone();
+        func.normalize_ssa();
+        func.erase_self_references();
+        if let Some(module) = module {
+            func.remap_function_pointers(|ptr| {
+                let name = match ptr {
+                    FunctionPointer::Internal(uuid) => {
+                        module.functions.get(uuid)?.name.as_deref()?
+                    }
+                    FunctionPointer::External(uuid) => {
+                        module.external_functions.get(uuid)?.name.as_str()
+                    }
+                };
+                let mut hasher = StableHasher::new();
+                name.hash(&mut hasher);
+                let uuid = Uuid::from_u64_pair(hasher.finish(), 0);
+                Some(match ptr {
+                    FunctionPointer::Internal(_) => FunctionPointer::Internal(uuid),
+                    FunctionPointer::External(_) => FunctionPointer::External(uuid),
+                })
+            });
+        }
+
+        let typerefs = func
+            .params
+            .iter()
+            .map(|(_, ty)| *ty)
+            .chain(func.return_type)
+            .chain(func.body.values().flat_map(|bb| {
+                bb.instructions
+                    .iter()
+                    .flat_map(|instr| instr.referenced_types())
+                    .chain(bb.terminator.referenced_types())
+            }))
+            .collect::<BTreeSet<_>>();
+        let mapping = typerefs
+            .into_iter()
+            .filter(|ty| !ty.is_wildcard())
+            .filter_map(|ty| {
+                registry
+                    .fingerprint(ty)
+                    .map(|fingerprint| (ty, Typeref::from_fingerprint(fingerprint)))
+            })
+            .collect();
+        func.remap_types(&mapping);
+
+        let mut hasher = StableHasher::new();
+        func.name.hash(&mut hasher);
+        func.params.hash(&mut hasher);
+        func.return_type.hash(&mut hasher);
+        func.body.hash(&mut hasher);
+        func.visibility.hash(&mut hasher);
+        func.cconv.hash(&mut hasher);
+        func.wildcard_types.hash(&mut hasher);
+        func.meta_function.hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Get analysis context for the function.
     pub fn analyze(self: Arc<Self>) -> FunctionAnalysis {
         FunctionAnalysis {
@@ -907,6 +2021,30 @@ impl Function {
         }
     }
 
+    /// Remap the function pointers referenced by the function.
+    ///
+    /// `mapping` is called on every function pointer immediate; returning `None` keeps
+    /// the pointer unchanged.
+    pub fn remap_function_pointers(
+        &mut self,
+        mut mapping: impl FnMut(&FunctionPointer) -> Option<FunctionPointer>,
+    ) {
+        for bb in self.body.values_mut() {
+            for operand in bb
+                .instructions
+                .iter_mut()
+                .flat_map(|instr| instr.operands_mut())
+                .chain(bb.terminator.operands_mut())
+            {
+                if let Operand::Imm(AnyConst::FuncPtr(ptr)) = operand
+                    && let Some(mapped) = mapping(ptr)
+                {
+                    *ptr = mapped;
+                }
+            }
+        }
+    }
+
     /// Remap types in the function according to the provided mapping.
     pub fn remap_types(&mut self, mapping: &BTreeMap<Typeref, Typeref>) {
         // Remap parameter types
@@ -934,6 +2072,17 @@ impl Function {
     }
 }
 
+/// A natural loop of a function, see [`Function::derive_loops`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NaturalLoop {
+    /// The block dominating every block of the loop, targeted by the back-edge.
+    pub header: Label,
+    /// The blocks of the loop, including the header.
+    pub body: BTreeSet<Label>,
+    /// The edge `(source, header)` closing the loop.
+    pub back_edge: (Label, Label),
+}
+
 /// Analyze context for a function.
 ///
 /// This contains a list of acceleration structures to speed up analysis and
@@ -948,6 +2097,28 @@ pub struct FunctionAnalysis {
     pub dest_map: BTreeMap<Name, InstructionRef>,
 }
 
+/// High-level summary of the differences between two modules, see [`Module::diff`].
+///
+/// Each list holds function names in lexicographic order.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ModuleDiff {
+    /// Functions only defined in the other module.
+    pub added: Vec<String>,
+    /// Functions only defined in this module.
+    pub removed: Vec<String>,
+    /// Functions defined in both modules whose definitions differ.
+    pub changed: Vec<String>,
+    /// Functions defined in both modules with structurally equal definitions.
+    pub unchanged: Vec<String>,
+}
+
+impl ModuleDiff {
+    /// Check whether both modules define the same functions.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
 /// A module containing defined functions and references to external ones.
 ///
 /// `Module` acts as the compilation unit boundary for symbol visibility.
@@ -1049,6 +2220,162 @@ impl Module {
             .map(|f| f.as_ref().uuid)
     }
 
+    /// Iterate over the internal functions sorted by name, then by UUID for ties.
+    ///
+    /// Unlike iterating over [`Module::functions`] directly (ordered by UUID), this
+    /// order is stable across runs, which makes it suitable for printing and golden
+    /// tests. Unnamed functions come first.
+    pub fn functions_by_name(&self) -> impl Iterator<Item = &Function> {
+        let mut functions: Vec<&Function> = self.functions.values().map(|f| f.as_ref()).collect();
+        functions.sort_by(|a, b| (&a.name, a.uuid).cmp(&(&b.name, b.uuid)));
+        functions.into_iter()
+    }
+
+    /// List the external functions referenced by the module that are not provided by
+    /// one of its internal functions, sorted by UUID.
+    ///
+    /// An external reference is considered resolved when an internal function shares
+    /// the name of the declared external function. The remaining symbols must be provided
+    /// by the linker.
+    pub fn unresolved_externals(&self) -> Vec<Uuid> {
+        let internal_names: BTreeSet<&str> = self
+            .functions
+            .values()
+            .filter_map(|f| f.name.as_deref())
+            .collect();
+
+        let referenced: BTreeSet<Uuid> = self
+            .functions
+            .values()
+            .flat_map(|f| f.body.values())
+            .flat_map(|bb| {
+                bb.instructions
+                    .iter()
+                    .flat_map(|instr| instr.operands())
+                    .chain(bb.terminator.operands())
+            })
+            .filter_map(|op| match op {
+                Operand::Imm(AnyConst::FuncPtr(FunctionPointer::External(uuid))) => Some(*uuid),
+                _ => None,
+            })
+            .collect();
+
+        referenced
+            .into_iter()
+            .filter(|uuid| {
+                self.external_functions
+                    .get(uuid)
+                    .is_none_or(|ext| !internal_names.contains(ext.name.as_str()))
+            })
+            .collect()
+    }
+
+    /// Map each of the [`Module::unresolved_externals`] to its symbol name.
+    ///
+    /// References to undeclared external functions are reported using their UUID.
+    pub fn external_symbol_names(&self) -> BTreeMap<Uuid, String> {
+        self.unresolved_externals()
+            .into_iter()
+            .map(|uuid| {
+                let name = self
+                    .external_functions
+                    .get(&uuid)
+                    .map(|ext| ext.name.clone())
+                    .unwrap_or_else(|| uuid.to_string());
+                (uuid, name)
+            })
+            .collect()
+    }
+
+    /// Map the UUIDs of the internal and external functions of `other` onto the UUIDs of
+    /// the functions of `self` sharing the same name. Unnamed functions are not mapped.
+    fn uuids_by_name(&self, other: &Module) -> BTreeMap<Uuid, Uuid> {
+        let internal_by_name: BTreeMap<&str, Uuid> = self
+            .functions
+            .values()
+            .filter_map(|f| f.name.as_deref().map(|name| (name, f.uuid)))
+            .collect();
+        let external_by_name: BTreeMap<&str, Uuid> = self
+            .external_functions
+            .values()
+            .map(|ext| (ext.name.as_str(), ext.uuid))
+            .collect();
+
+        let internal = other.functions.values().filter_map(|f| {
+            let uuid = internal_by_name.get(f.name.as_deref()?)?;
+            Some((f.uuid, *uuid))
+        });
+        let external = other.external_functions.values().filter_map(|ext| {
+            let uuid = external_by_name.get(ext.name.as_str())?;
+            Some((ext.uuid, *uuid))
+        });
+        internal.chain(external).collect()
+    }
+
+    /// Clone `function` under the UUID `uuid`, rewriting the functions it references
+    /// through `mapping` (see [`Module::uuids_by_name`]).
+    fn remap_function_uuids(
+        function: &Function,
+        uuid: Uuid,
+        mapping: &BTreeMap<Uuid, Uuid>,
+    ) -> Function {
+        let mut function = function.clone();
+        function.uuid = uuid;
+        function.remap_function_pointers(|ptr| match ptr {
+            FunctionPointer::Internal(uuid) => mapping
+                .get(uuid)
+                .map(|uuid| FunctionPointer::Internal(*uuid)),
+            FunctionPointer::External(uuid) => mapping
+                .get(uuid)
+                .map(|uuid| FunctionPointer::External(*uuid)),
+        });
+        function
+    }
+
+    /// Compare the internal functions of this module against the ones of `other`.
+    ///
+    /// Functions are matched by name (unnamed functions are ignored), and functions
+    /// present in both modules are compared with [`Function::structurally_equal`].
+    /// Calls to internal or external functions are matched by name as well, such that
+    /// the same source parsed twice yields no difference. Both modules are expected to
+    /// share the same type registry.
+    pub fn diff(&self, other: &Module) -> ModuleDiff {
+        fn named_functions(module: &Module) -> BTreeMap<&str, &Function> {
+            module
+                .functions
+                .values()
+                .filter_map(|f| f.name.as_deref().map(|name| (name, f.as_ref())))
+                .collect()
+        }
+
+        let lhs = named_functions(self);
+        let rhs = named_functions(other);
+        let mapping = self.uuids_by_name(other);
+
+        let mut diff = ModuleDiff::default();
+        for (name, function) in &lhs {
+            let Some(other_function) = rhs.get(name) else {
+                diff.removed.push(name.to_string());
+                continue;
+            };
+
+            let other_function =
+                Self::remap_function_uuids(other_function, function.uuid, &mapping);
+            if function.structurally_equal(&other_function) {
+                diff.unchanged.push(name.to_string());
+            } else {
+                diff.changed.push(name.to_string());
+            }
+        }
+        diff.added = rhs
+            .keys()
+            .filter(|name| !lhs.contains_key(*name))
+            .map(|name| name.to_string())
+            .collect();
+
+        diff
+    }
+
     /// Retrieve a particular function from its Uuid
     pub fn get_internal_function_by_uuid(&self, uuid: Uuid) -> Option<&Function> {
         self.functions.get(&uuid).map(|f| f.as_ref())
@@ -1061,6 +2388,62 @@ impl Module {
             .and_then(|arc| Arc::get_mut(arc))
     }
 
+    /// Compare two modules while ignoring meta-instructions.
+    ///
+    /// Internal and external functions are matched by name (unnamed functions by UUID),
+    /// and calls are matched by name as well, as done by [`Module::diff`]. Matching
+    /// functions are compared with [`Function::structurally_equal`] on clones after
+    /// calling [`Function::strip_meta`], and the `meta_function` flag is ignored. Two
+    /// modules that only differ in their verification annotations, even when parsed
+    /// separately, are therefore considered equal. This allows reusing executable
+    /// artifacts across annotation changes. Both modules are expected to be verified
+    /// and to share the same type registry.
+    ///
+    /// Functions whose executable code depends on meta results cannot be stripped (see
+    /// [`Error::MetaResultInUse`]); they are compared with their meta-instructions.
+    pub fn equal_ignoring_meta(&self, other: &Module) -> bool {
+        fn canonicalize(function: &Function) -> Function {
+            let mut function = function.clone();
+            // On failure the function is left unchanged: meta results used by the
+            // executable code are part of it, hence compared as well
+            function.strip_meta().ok();
+            function.meta_function = false;
+            function
+        }
+
+        if self.external_functions.len() != other.external_functions.len()
+            || self.functions.len() != other.functions.len()
+        {
+            return false;
+        }
+
+        let mapping = self.uuids_by_name(other);
+        let externals_match = other.external_functions.values().all(|ext| {
+            mapping
+                .get(&ext.uuid)
+                .and_then(|uuid| self.external_functions.get(uuid))
+                .is_some_and(|lhs| {
+                    *lhs == ExternalFunction {
+                        uuid: lhs.uuid,
+                        ..ext.clone()
+                    }
+                })
+        });
+        if !externals_match {
+            return false;
+        }
+
+        let mut matched = BTreeSet::new();
+        other.functions.values().all(|rhs| {
+            let uuid = mapping.get(&rhs.uuid).copied().unwrap_or(rhs.uuid);
+            let Some(lhs) = self.functions.get(&uuid) else {
+                return false;
+            };
+            let rhs = Self::remap_function_uuids(rhs, uuid, &mapping);
+            matched.insert(uuid) && canonicalize(lhs).structurally_equal(&canonicalize(&rhs))
+        })
+    }
+
     /// Check each function in the module for SSA validity.
     pub fn verify(&self) -> Result<(), Error> {
         for func in self.functions.values() {
@@ -1081,6 +2464,274 @@ impl Module {
         Ok(())
     }
 
+    /// Normalize, verify and type check every function of the module in one call.
+    ///
+    /// For each function, wildcard types are regenerated, the function is verified,
+    /// its SSA names are normalized (see [`Function::normalize_ssa`]) and it is type
+    /// checked against `registry`. Finally, references to other functions are checked
+    /// with [`Module::verify_func`]. Verification runs before normalization as the latter
+    /// requires every used name to be defined.
+    ///
+    /// Returns the first error encountered. Functions processed before the error are
+    /// left normalized.
+    pub fn finalize(&mut self, registry: &TypeRegistry) -> Result<(), Error> {
+        for func in self.functions.values_mut() {
+            let function = Arc::make_mut(func);
+            function.generate_wildcards();
+            function.verify()?;
+            function.normalize_ssa();
+            function.type_check(registry)?;
+        }
+
+        for func in self.functions.values() {
+            self.verify_func(func)?;
+        }
+
+        Ok(())
+    }
+
+    /// Inline the `invoke` instruction at `call_site` of the internal function `caller`.
+    ///
+    /// The calling block is split after the call site: its remaining instructions and
+    /// terminator move to a fresh continuation block. A copy of the callee's body is
+    /// inserted in between, with SSA names and labels remapped to fresh ones (see
+    /// [`Function::next_available_name`] and [`Function::next_available_label`]).
+    /// Parameters are substituted by the call arguments, and the value returned by
+    /// the callee replaces every use of the call destination.
+    ///
+    /// Only callees with a single `ret` block, no wildcard types and no direct call to
+    /// themselves or to `caller` are supported. Meta-instructions of the callee are
+    /// stripped (see [`Function::strip_meta`]) unless `caller` is a meta function;
+    /// callees whose code depends on meta results can then not be inlined. Spans of the
+    /// inlined instructions are not carried over.
+    pub fn inline_function(
+        &mut self,
+        caller: Uuid,
+        call_site: InstructionRef,
+    ) -> Result<(), Error> {
+        let caller_fn =
+            self.functions
+                .get(&caller)
+                .ok_or_else(|| Error::UndefinedInternalFunction {
+                    function: caller.to_string(),
+                    undefined: caller,
+                })?;
+        let caller_name = || caller_fn.name.clone().unwrap_or_else(|| caller.to_string());
+
+        let invalid_call_site = || Error::InvalidCallSite {
+            function: caller_name(),
+            block: call_site.block,
+            index: call_site.index,
+        };
+        let invoke = caller_fn
+            .body
+            .get(&call_site.block)
+            .and_then(|bb| bb.instructions.get(call_site.index as usize))
+            .and_then(|instr| instr.try_as_invoke_ref())
+            .ok_or_else(invalid_call_site)?
+            .clone();
+        let Operand::Imm(AnyConst::FuncPtr(FunctionPointer::Internal(callee))) = invoke.function
+        else {
+            return Err(invalid_call_site());
+        };
+
+        let callee_fn =
+            self.functions
+                .get(&callee)
+                .ok_or_else(|| Error::UndefinedInternalFunction {
+                    function: caller_name(),
+                    undefined: callee,
+                })?;
+        let unsupported = |reason: &str| Error::InlineUnsupported {
+            caller: caller_name(),
+            callee: callee_fn.name.clone().unwrap_or_else(|| callee.to_string()),
+            reason: reason.to_string(),
+        };
+
+        let calls_back = callee_fn.body.values().any(|bb| {
+            bb.instructions
+                .iter()
+                .flat_map(|instr| instr.operands())
+                .chain(bb.terminator.operands())
+                .any(|op| {
+                    matches!(
+                        op,
+                        Operand::Imm(AnyConst::FuncPtr(FunctionPointer::Internal(uuid)))
+                            if *uuid == callee || *uuid == caller
+                    )
+                })
+        });
+        if calls_back {
+            return Err(unsupported("recursive calls cannot be inlined"));
+        }
+        if callee_fn
+            .body
+            .values()
+            .filter(|bb| bb.terminator.is_ret())
+            .count()
+            != 1
+        {
+            return Err(unsupported("the callee must have exactly one `ret` block"));
+        }
+        if !callee_fn.wildcard_types.is_empty() {
+            return Err(unsupported("generic callees cannot be inlined"));
+        }
+        if invoke.args.len() != callee_fn.params.len() {
+            return Err(unsupported(
+                "argument count does not match the callee parameters",
+            ));
+        }
+
+        // Meta-instructions are only allowed within meta functions
+        let mut inlined = callee_fn.as_ref().clone();
+        if !caller_fn.meta_function && inlined.strip_meta().is_err() {
+            return Err(unsupported(
+                "the callee uses meta results but the caller is not a meta function",
+            ));
+        }
+
+        // Rename the callee names and labels into fresh ones of the caller
+        let first_name = caller_fn.next_available_name();
+        let names: BTreeMap<Name, Name> = inlined
+            .params
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(
+                inlined
+                    .body
+                    .values()
+                    .flat_map(|bb| bb.instructions.iter().filter_map(|i| i.destination())),
+            )
+            .zip((first_name.0..).map(Name))
+            .collect();
+
+        let mut next_label = caller_fn.next_available_label();
+        let mut labels = BTreeMap::new();
+        for label in inlined.body.keys() {
+            labels.insert(*label, next_label);
+            next_label = Label(next_label.0 + 1);
+        }
+        let continuation = next_label;
+
+        let mut returned = None;
+        let mut blocks = vec![];
+        for (_, mut bb) in std::mem::take(&mut inlined.body) {
+            bb.label = labels[&bb.label];
+            for instr in bb.instructions.iter_mut() {
+                if let Some(dest) = instr.destination() {
+                    instr.set_destination(names[&dest]);
+                }
+                instr.remap_operands(|name| names.get(&name).copied());
+                if let HyInstr::Phi(phi) = instr {
+                    for (_, label) in phi.values.iter_mut() {
+                        *label = labels[label];
+                    }
+                } else if let HyInstr::MetaAnalysisStat(stat) = instr
+                    && let AnalysisStatistic::TerminationBehavior(TerminationScope::ReachAny(
+                        reached,
+                    )) = &mut stat.statistic
+                {
+                    for label in reached.iter_mut() {
+                        *label = labels[label];
+                    }
+                }
+            }
+            bb.terminator
+                .remap_operands(|name| names.get(&name).copied());
+            for target in bb.terminator.iter_targets_mut() {
+                *target = labels[target];
+            }
+
+            if let terminator::HyTerminator::Ret(ret) = &bb.terminator {
+                returned = ret.value.clone();
+                bb.terminator = Jump {
+                    target: continuation,
+                }
+                .into();
+            }
+            blocks.push(bb);
+        }
+
+        // Substitute parameters by the call arguments
+        inlined.body = blocks.into_iter().map(|bb| (bb.label, bb)).collect();
+        for ((param, _), arg) in callee_fn.params.iter().zip(invoke.args.iter()) {
+            inlined.substitute_operand(names[param], arg.clone());
+        }
+        if let Some(Operand::Reg(name)) = &returned
+            && let Some(index) = callee_fn
+                .params
+                .iter()
+                .position(|(param, _)| names[param] == *name)
+        {
+            returned = Some(invoke.args[index].clone());
+        }
+        if invoke.dest.is_some() && returned.is_none() {
+            return Err(unsupported("the callee does not return a value"));
+        }
+        let entry = *labels
+            .get(&Label::NIL)
+            .ok_or_else(|| unsupported("the callee has no entry block"))?;
+
+        // Split the calling block at the call site
+        let function = Arc::make_mut(self.functions.get_mut(&caller).unwrap());
+        let bb = function.body.get_mut(&call_site.block).unwrap();
+        let index = call_site.index as usize;
+        let tail = bb.instructions.split_off(index + 1);
+        bb.instructions.pop();
+        let terminator = std::mem::replace(&mut bb.terminator, Jump { target: entry }.into());
+        let successors: Vec<Label> = terminator.iter_targets().map(|(t, _)| t).collect();
+        function.body.insert(
+            continuation,
+            BasicBlock {
+                label: continuation,
+                instructions: tail,
+                terminator,
+            },
+        );
+        function.body.extend(inlined.body);
+
+        // Successors are now reached from the continuation block
+        for successor in successors {
+            let Some(successor_bb) = function.body.get_mut(&successor) else {
+                continue;
+            };
+            for instr in successor_bb.instructions.iter_mut() {
+                if let HyInstr::Phi(phi) = instr {
+                    for (_, label) in phi.values.iter_mut() {
+                        if *label == call_site.block {
+                            *label = continuation;
+                        }
+                    }
+                }
+            }
+        }
+
+        function.spans = std::mem::take(&mut function.spans)
+            .into_iter()
+            .filter_map(|(reference, span)| {
+                if reference.block != call_site.block || reference.index < call_site.index {
+                    Some((reference, span))
+                } else if reference.index > call_site.index {
+                    Some((
+                        InstructionRef::from((
+                            continuation,
+                            (reference.index - call_site.index - 1) as usize,
+                        )),
+                        span,
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        if let (Some(dest), Some(value)) = (invoke.dest, returned) {
+            function.substitute_operand(dest, value);
+        }
+
+        Ok(())
+    }
+
     /// Remap types in the module according to the provided mapping.
     pub fn remap_types(&mut self, mapping: &BTreeMap<Typeref, Typeref>) {
         // Remap types in each function
@@ -80,7 +80,10 @@ impl Label {
 }
 
 /// Instruction operand.
-#[derive(Clone, Debug, PartialEq, Eq, Hash, EnumIs, EnumTryAs)]
+///
+/// Operands are ordered by kind (registers, then immediates, then undefined values),
+/// then by name, constant or type.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIs, EnumTryAs)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(
     feature = "borsh",
@@ -1,6 +1,7 @@
 use std::{
     cell::RefCell,
     collections::{BTreeMap, HashMap},
+    ops::Range,
     path::Path,
     rc::Rc,
     str::FromStr,
@@ -26,12 +27,12 @@ use uuid::Uuid;
 use crate::{
     consts::{AnyConst, fp::FConst, int::IConst},
     modules::{
-        BasicBlock, CallingConvention, Function, Instruction, Module, Visibility,
+        BasicBlock, CallingConvention, Function, Instruction, InstructionRef, Module, Visibility,
         instructions::{
             HyInstr, HyInstrOp, InstructionFlags, fp::*, int::*, mem::*, meta::*, misc::*,
         },
         operand::{Label, Name, Operand},
-        symbol::{FunctionPointer, FunctionPointerType},
+        symbol::{ExternalFunction, FunctionPointer, FunctionPointerType},
         terminator::*,
     },
     types::{
@@ -45,6 +46,11 @@ use crate::{
 type Span = SimpleSpan;
 type Spanned<T> = (T, Span);
 
+/// Convert a range of token indices into the byte range it covers in the source.
+fn token_span_to_source(spans: &[Span], tokens: Range<usize>) -> Range<usize> {
+    spans[tokens.start].start..spans[tokens.end - 1].end
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, EnumIs, EnumTryAs, EnumDiscriminants)]
 enum Token<'a> {
     // Special identifiers and keywords
@@ -966,6 +972,11 @@ where
             just_match(TokenDiscriminants::InstrOp)
                 .map(|x| x.try_as_instr_op().unwrap()),
         )
+        .then(
+            just_match(TokenDiscriminants::CallingConvention)
+                .map(|x| x.try_as_calling_convention().unwrap())
+                .or_not(),
+        )
         .then(
             type_parser().then_ignore(just(Token::Comma)).or_not()
         )
@@ -1008,7 +1019,7 @@ where
         )
         .validate(move |(((elem, labels), align), volatile), extra, emit| {
             let state: &mut SimpleState<State<'src>> = extra.state();
-            let (((destination, op), op_additional_ty), operand) = elem;
+            let ((((destination, op), cconv), op_additional_ty), operand) = elem;
             let (op, variant) = op;
             let dest_and_ty = if let Some((dest, ty)) = destination {
                 Some((state.get_register(dest), ty))
@@ -1038,6 +1049,16 @@ where
                 return HyInstr::MetaAssert(MetaAssert { condition: Operand::Imm(IConst::from(1u64).into()) });
             }
 
+            if cconv.is_some() && op != HyInstrOp::Invoke {
+                emit.emit(Rich::custom(
+                    extra.span(),
+                    format!(
+                        "calling convention is only valid for invoke instructions, got {} instruction",
+                        op.opname()
+                    ),
+                ));
+            }
+
             if op != HyInstrOp::Phi && matches!(operand, Either::Right(_)) {
                 emit.emit(Rich::custom(
                     extra.span(),
@@ -1493,7 +1514,7 @@ where
 
                     let function = operands.remove(0);
 
-                    Invoke { dest, ty, function, args: operands, cconv: None }.into()
+                    Invoke { dest, ty, function, args: operands, cconv }.into()
                 },
                 HyInstrOp::Phi => {
                     let (dest, ty) = dest_and_ty.unwrap();
@@ -1882,16 +1903,23 @@ where
     let block = block_label
         .then(
             parse_instruction()
+                .map_with(|instr, extra| (instr, extra.span()))
                 .separated_by(just(Token::Newline))
                 .collect::<Vec<_>>(),
         )
         .then_ignore(just(Token::Newline).or_not())
         .then(parse_terminator())
         .then_ignore(just(Token::Newline).or_not())
-        .map(|((label, instructions), terminator)| BasicBlock {
-            label,
-            instructions,
-            terminator,
+        .map(|((label, instructions), terminator)| {
+            let (instructions, spans): (Vec<_>, Vec<_>) = instructions.into_iter().unzip();
+            (
+                BasicBlock {
+                    label,
+                    instructions,
+                    terminator,
+                },
+                spans,
+            )
         });
 
     let meta_arguments = any()
@@ -1976,15 +2004,27 @@ where
                 }
             }
 
+            // Spans are expressed in token indices here, they are converted to source
+            // byte offsets by the caller (see `token_span_to_source`).
+            let spans = blocks
+                .iter()
+                .flat_map(|(block, spans)| {
+                    spans.iter().enumerate().map(|(index, span): (usize, &Span)| {
+                        (InstructionRef::from((block.label, index)), span.start..span.end)
+                    })
+                })
+                .collect();
+
             let func = Function {
                 uuid,
                 name: Some(func_name.to_string()),
                 params,
                 return_type: ty.left(),
-                body: blocks.into_iter().map(|block| (block.label, block)).collect(),
+                body: blocks.into_iter().map(|(block, _)| (block.label, block)).collect(),
                 visibility,
                 cconv,
                 meta_function: is_meta_func,
+                spans,
                 ..Default::default()
             };
 
@@ -2024,9 +2064,57 @@ where
         .labelled("import statement")
 }
 
-// Final parser, import + function definitions
+fn declare_parser<'src, I>() -> impl Parser<'src, I, ExternalFunction, Extra<'src>> + Clone
+where
+    I: ValueInput<'src, Token = Token<'src>, Span = Span> + Clone,
+{
+    let param_types = type_parser()
+        .separated_by(just(Token::Comma))
+        .collect::<Vec<_>>()
+        .delimited_by(just(Token::LParen), just(Token::RParen));
+
+    // declare <ret-type> [cc] [external] <name>(<types>)
+    fast_boxed!(
+        just(Token::Identifier("declare", vec![]))
+            .ignore_then(
+                type_parser()
+                    .map(Either::Left)
+                    .or(just(Token::Void).map(Either::Right))
+            )
+            .then(
+                just_match(TokenDiscriminants::CallingConvention)
+                    .map(|x| x.try_as_calling_convention().unwrap())
+                    .or_not(),
+            )
+            .then_ignore(just(Token::Identifier("external", vec![])).or_not())
+            .then(just_match(TokenDiscriminants::Identifier).map(|token| {
+                let (name, parts) = token.try_as_identifier().unwrap();
+                let mut full_name = name.to_string();
+                for part in parts {
+                    full_name.push('.');
+                    full_name.push_str(part);
+                }
+                full_name
+            }),)
+            .then(param_types)
+            .map_with(|(((ty, cconv), name), param_types), extra| {
+                let state: &mut SimpleState<State<'src>> = extra.state();
+                ExternalFunction {
+                    uuid: (state.uuid_generator)(),
+                    name,
+                    cconv: cconv.unwrap_or_default(),
+                    param_types,
+                    return_type: ty.left(),
+                }
+            })
+            .labelled("external function declaration")
+    )
+}
+
+// Final parser, import + declarations + function definitions
 enum Item {
     Import(String),
+    Declaration(ExternalFunction),
     Function(Function),
 }
 
@@ -2039,6 +2127,7 @@ where
             .or_not()
             .ignore_then(choice((
                 import_parser().map(Item::Import),
+                declare_parser().map(Item::Declaration),
                 parse_function().map(Item::Function),
             )))
             .then_ignore(just(Token::Newline).or_not())
@@ -2047,6 +2136,54 @@ where
     )
 }
 
+/// Match external function references that were not found in the module against the
+/// freshly parsed `declare` statements.
+///
+/// Returns the mapping from placeholder UUIDs to the UUIDs of the declarations.
+fn resolve_external_functions(
+    module: &Module,
+    unresolved: &HashMap<String, Uuid>,
+    declared: &[ExternalFunction],
+) -> Result<HashMap<Uuid, Uuid>, Error> {
+    let mut by_name: HashMap<&str, Uuid> = HashMap::new();
+    for external in declared {
+        let already_declared = module
+            .find_function_uuid_by_name(&external.name, FunctionPointerType::External)
+            .is_some();
+        if already_declared || by_name.insert(&external.name, external.uuid).is_some() {
+            error!(
+                "External function declared more than once: {}",
+                external.name
+            );
+            return Err(Error::FunctionAlreadyExists {
+                name: external.name.clone(),
+            });
+        }
+    }
+
+    let mut resolved = HashMap::new();
+    let mut missing = vec![];
+    for (name, uuid) in unresolved {
+        match by_name.get(name.as_str()) {
+            Some(declared_uuid) => {
+                resolved.insert(*uuid, *declared_uuid);
+            }
+            None => missing.push(name.clone()),
+        }
+    }
+
+    if !missing.is_empty() {
+        missing.sort();
+        error!("Unresolved external functions: {:?}", missing);
+        return Err(Error::UnresolvedFunction {
+            name: missing.join(", "),
+            func_type: FunctionPointerType::External,
+        });
+    }
+
+    Ok(resolved)
+}
+
 /// Extend a module by parsing a file at the given path, including handling imports
 /// recursively.
 ///
@@ -2082,6 +2219,7 @@ pub fn extend_module_from_path(
     let unresolved_internal_functions: RefCell<HashMap<String, Uuid>> = Default::default();
     let unresolved_external_functions: RefCell<HashMap<String, Uuid>> = Default::default();
     let mut list_added_internal_functions = vec![];
+    let mut list_added_external_functions = vec![];
 
     while let Some(current_path) = stack.pop() {
         // Read the source file
@@ -2129,14 +2267,15 @@ pub fn extend_module_from_path(
             {
                 Some(func_ptr)
             } else {
-                let uuid = Uuid::new_v4();
-                match func_type {
-                    FunctionPointerType::External => unresolved_external_functions
+                let uuid = match func_type {
+                    FunctionPointerType::External => *unresolved_external_functions
                         .borrow_mut()
-                        .insert(name, uuid),
-                    FunctionPointerType::Internal => unresolved_internal_functions
+                        .entry(name)
+                        .or_insert_with(Uuid::new_v4),
+                    FunctionPointerType::Internal => *unresolved_internal_functions
                         .borrow_mut()
-                        .insert(name, uuid),
+                        .entry(name)
+                        .or_insert_with(Uuid::new_v4),
                 };
                 Some(uuid)
             }
@@ -2199,8 +2338,15 @@ pub fn extend_module_from_path(
                         })?;
                     stack.push(canonical_import_path);
                 }
+                Item::Declaration(external) => {
+                    debug!("Declaring external function {:?}", external.name);
+                    list_added_external_functions.push(external);
+                }
                 Item::Function(mut function) => {
                     debug!("Adding function {:?} to module", function.name);
+                    for span in function.spans.values_mut() {
+                        *span = token_span_to_source(&spans, span.clone());
+                    }
                     function.normalize_ssa();
 
                     // Add it to the list functions to be added after verification
@@ -2234,21 +2380,16 @@ pub fn extend_module_from_path(
         resolved_internal_functions.insert(*uuid, function.uuid);
     }
 
-    // For no external functions cannot be defined by the module as such all unresolved external is treated as an error;
-    if !unresolved_external_functions.borrow().is_empty() {
-        let names: Vec<String> = unresolved_external_functions
-            .borrow()
-            .keys()
-            .cloned()
-            .collect();
-        error!("Unresolved external functions: {:?}", names);
-        return Err(Error::UnresolvedFunction {
-            name: names.join(", "),
-            func_type: FunctionPointerType::External,
-        });
-    }
+    let resolved_external_functions = resolve_external_functions(
+        module,
+        &unresolved_external_functions.borrow(),
+        &list_added_external_functions,
+    )?;
 
     // Finally update all the links internally
+    for external in list_added_external_functions {
+        module.external_functions.insert(external.uuid, external);
+    }
     for mut func in list_added_internal_functions.into_iter() {
         for (_, block) in func.body.iter_mut() {
             for operands in block
@@ -2266,7 +2407,11 @@ pub fn extend_module_from_path(
                                 *uuid = *new_uuid;
                             }
                         }
-                        FunctionPointer::External(_) => {}
+                        FunctionPointer::External(uuid) => {
+                            if let Some(resolved) = resolved_external_functions.get(uuid) {
+                                *uuid = *resolved;
+                            }
+                        }
                     }
                 }
             }
@@ -2330,6 +2475,7 @@ pub fn extend_module_from_string(
     let unresolved_internal_functions: RefCell<HashMap<String, Uuid>> = Default::default();
     let unresolved_external_functions: RefCell<HashMap<String, Uuid>> = Default::default();
     let mut list_added_internal_functions = vec![];
+    let mut list_added_external_functions = vec![];
 
     {
         let func_retriever = Rc::new(|name: String, func_type: FunctionPointerType| {
@@ -2412,8 +2558,15 @@ pub fn extend_module_from_string(
                         tokens: tokens.iter().map(|t| format!("{:?}", t)).collect(),
                     });
                 }
+                Item::Declaration(external) => {
+                    debug!("Declaring external function {:?}", external.name);
+                    list_added_external_functions.push(external);
+                }
                 Item::Function(mut function) => {
                     debug!("Adding function {:?} to module", function.name);
+                    for span in function.spans.values_mut() {
+                        *span = token_span_to_source(&spans, span.clone());
+                    }
                     function.normalize_ssa();
                     list_added_internal_functions.push(function);
                 }
@@ -2443,23 +2596,19 @@ pub fn extend_module_from_string(
         resolved_internal_functions.insert(*uuid, function.uuid);
     }
 
-    // External functions cannot be defined in the string source; treat unresolved externals as error
-    if !unresolved_external_functions.borrow().is_empty() {
-        let names: Vec<String> = unresolved_external_functions
-            .borrow()
-            .keys()
-            .cloned()
-            .collect();
-        error!("Unresolved external functions: {:?}", names);
-        return Err(Error::UnresolvedFunction {
-            name: names.join(", "),
-            func_type: FunctionPointerType::External,
-        });
-    }
+    // External functions must be declared (either previously in the module or in the source)
+    let resolved_external_functions = resolve_external_functions(
+        module,
+        &unresolved_external_functions.borrow(),
+        &list_added_external_functions,
+    )?;
 
     // Update all internal function pointer links and insert functions into the module
     // Ensure parser state is dropped to release any immutable borrows on `module`.
     // parser state and func_retriever have been dropped by leaving scope above
+    for external in list_added_external_functions {
+        module.external_functions.insert(external.uuid, external);
+    }
     for mut func in list_added_internal_functions.into_iter() {
         for (_, block) in func.body.iter_mut() {
             for operands in block
@@ -2477,7 +2626,11 @@ pub fn extend_module_from_string(
                                 *uuid = *resolved;
                             }
                         }
-                        FunctionPointer::External(_) => {}
+                        FunctionPointer::External(uuid) => {
+                            if let Some(resolved) = resolved_external_functions.get(uuid) {
+                                *uuid = *resolved;
+                            }
+                        }
                     }
                 }
             }
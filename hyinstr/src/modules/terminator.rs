@@ -292,6 +292,19 @@ impl HyTerminator {
             HyTerminator::Trap(_) => std::iter::empty(),
         }
     }
+
+    /// Mutably iterate over branch target labels, allowing edges to be redirected.
+    #[auto_enum(Iterator)]
+    pub fn iter_targets_mut(&mut self) -> impl Iterator<Item = &'_ mut Label> + '_ {
+        match self {
+            HyTerminator::Branch(cbranch) => {
+                [&mut cbranch.target_true, &mut cbranch.target_false].into_iter()
+            }
+            HyTerminator::Jump(jump) => std::iter::once(&mut jump.target),
+            HyTerminator::Ret(_) => std::iter::empty(),
+            HyTerminator::Trap(_) => std::iter::empty(),
+        }
+    }
 }
 
 macro_rules! define_instr_any_instr {
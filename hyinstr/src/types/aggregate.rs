@@ -7,7 +7,12 @@
 //!
 //! Both types carry lightweight `fmt` helpers that accept a `&TypeRegistry`] so
 //! that elements can be resolved for display purposes.
-use std::{borrow::Borrow, collections::BTreeMap, fmt::Debug, ops::Deref};
+use std::{
+    borrow::{Borrow, Cow},
+    collections::BTreeMap,
+    fmt::Debug,
+    ops::Deref,
+};
 
 use crate::types::{AnyType, TypeRegistry, Typeref};
 #[cfg(feature = "serde")]
@@ -35,37 +40,65 @@ pub struct ArrayType {
     pub num_elements: u16,
 }
 
+/// Format the element `typeref` of an aggregate type.
+///
+/// `ancestors` holds the types currently being formatted, an element referring to one of
+/// them is printed as `<recursive>` instead of recursing forever.
+fn fmt_element(
+    f: &mut std::fmt::Formatter<'_>,
+    ref_object: &BTreeMap<Uuid, AnyType>,
+    typeref: Typeref,
+    ancestors: &[Uuid],
+) -> std::fmt::Result {
+    if ancestors.contains(&typeref.0) {
+        return write!(f, "<recursive>");
+    }
+
+    match ref_object.get(&typeref.0) {
+        Some(elem) => {
+            let mut ancestors = ancestors.to_vec();
+            ancestors.push(typeref.0);
+            write!(f, "{}", elem.internal_fmt(ref_object, &ancestors))
+        }
+        None => write!(f, "<unknown type {}>", typeref.0),
+    }
+}
+
 impl ArrayType {
-    pub(super) fn internal_fmt<U>(&self, ref_object: U) -> impl std::fmt::Display
+    pub(super) fn internal_fmt<U>(
+        &self,
+        ref_object: U,
+        ancestors: &[Uuid],
+    ) -> impl std::fmt::Display
     where
         U: Deref<Target = BTreeMap<Uuid, AnyType>> + Sized,
     {
-        struct ArrayTypeFmt<'a, U> {
+        struct ArrayTypeFmt<'a, 'b, U> {
             r#ref: &'a ArrayType,
             ref_object: U,
+            ancestors: &'b [Uuid],
         }
 
-        impl<U: Deref<Target = BTreeMap<Uuid, AnyType>> + Sized> std::fmt::Display for ArrayTypeFmt<'_, U> {
+        impl<U: Deref<Target = BTreeMap<Uuid, AnyType>> + Sized> std::fmt::Display
+            for ArrayTypeFmt<'_, '_, U>
+        {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                let elem = self.ref_object.borrow().get(&self.r#ref.ty.0).unwrap();
-                write!(
-                    f,
-                    "[ {} x {} ]",
-                    self.r#ref.num_elements,
-                    (*elem).internal_fmt(self.ref_object.deref()),
-                )
+                write!(f, "[ {} x ", self.r#ref.num_elements)?;
+                fmt_element(f, self.ref_object.borrow(), self.r#ref.ty, self.ancestors)?;
+                write!(f, " ]")
             }
         }
 
         ArrayTypeFmt {
             r#ref: self,
             ref_object,
+            ancestors,
         }
     }
 
     /// Build a formatting helper for this `ArrayType`.
     pub fn fmt<'a>(&'a self, registry: &'a TypeRegistry) -> impl std::fmt::Display {
-        self.internal_fmt(registry.array.read_recursive())
+        registry.fmt_type(Cow::Owned((*self).into()))
     }
 }
 
@@ -91,17 +124,22 @@ pub struct StructType {
 }
 
 impl StructType {
-    pub(super) fn internal_fmt<U>(&self, ref_object: U) -> impl std::fmt::Display
+    pub(super) fn internal_fmt<U>(
+        &self,
+        ref_object: U,
+        ancestors: &[Uuid],
+    ) -> impl std::fmt::Display
     where
         U: Deref<Target = BTreeMap<Uuid, AnyType>> + Sized,
     {
-        struct StructTypeFmt<'a, U> {
+        struct StructTypeFmt<'a, 'b, U> {
             r#ref: &'a StructType,
             ref_object: U,
+            ancestors: &'b [Uuid],
         }
 
         impl<U: Deref<Target = BTreeMap<Uuid, AnyType>> + Sized> std::fmt::Display
-            for StructTypeFmt<'_, U>
+            for StructTypeFmt<'_, '_, U>
         {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 if self.r#ref.packed {
@@ -111,13 +149,12 @@ impl StructType {
 
                 let mut first = true;
                 for typeref in self.r#ref.element_types.iter() {
-                    let elem = self.ref_object.deref().get(&typeref.0).unwrap();
                     if !first {
                         write!(f, ", ")?;
                     } else {
                         first = false;
                     }
-                    write!(f, "{}", elem.internal_fmt(self.ref_object.deref()))?;
+                    fmt_element(f, self.ref_object.deref(), *typeref, self.ancestors)?;
                 }
 
                 write!(f, "}}")
@@ -127,11 +164,12 @@ impl StructType {
         StructTypeFmt {
             r#ref: self,
             ref_object,
+            ancestors,
         }
     }
 
     /// Build a formatting helper for this `StructType`.
     pub fn fmt<'a>(&'a self, registry: &'a TypeRegistry) -> impl std::fmt::Display {
-        self.internal_fmt(registry.array.read_recursive())
+        registry.fmt_type(Cow::Owned(self.clone().into()))
     }
 }
@@ -0,0 +1,302 @@
+//! Memory layout of types
+//!
+//! This file computes the byte size and alignment of types stored in the
+//! [`TypeRegistry`], parameterized by a target [`DataLayout`]:
+//! - Scalars (integers, floating-points) occupy their store size rounded up to
+//!   their alignment, which is the next power of two capped by the data layout.
+//! - Fixed vectors are laid out like a scalar of their total bit width.
+//! - Arrays are `num_elements` consecutive elements.
+//! - Structures insert padding so that every element is aligned, unless packed.
+//!
+//! Types without a static layout (scalable vectors, extension types, wildcards,
+//! labels) have neither a size nor an alignment.
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::types::{
+    AnyType, TypeRegistry, Typeref,
+    aggregate::{ArrayType, StructType},
+    primary::{PrimaryBasicType, PrimaryType, VcSize, VcType},
+};
+
+/// Target dependent parameters used to compute type layouts.
+///
+/// All sizes and alignments are expressed in bytes. Alignments must be powers of two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "borsh",
+    derive(borsh::BorshSerialize, borsh::BorshDeserialize)
+)]
+pub struct DataLayout {
+    /// Size of a pointer.
+    pub pointer_size: u64,
+    /// Alignment of a pointer.
+    pub pointer_align: u64,
+    /// Largest alignment given to integer and floating-point scalars.
+    pub max_scalar_align: u64,
+    /// Largest alignment given to vectors.
+    pub max_vector_align: u64,
+}
+
+impl DataLayout {
+    /// Layout of a typical 64-bit target.
+    pub const LP64: Self = Self {
+        pointer_size: 8,
+        pointer_align: 8,
+        max_scalar_align: 16,
+        max_vector_align: 16,
+    };
+
+    /// Layout of a typical 32-bit target.
+    pub const ILP32: Self = Self {
+        pointer_size: 4,
+        pointer_align: 4,
+        max_scalar_align: 8,
+        max_vector_align: 16,
+    };
+
+    /// Layout of a value occupying `store_size` bytes with an alignment capped by `max_align`.
+    fn sized(store_size: u64, max_align: u64) -> (u64, u64) {
+        let align = store_size.max(1).next_power_of_two().min(max_align);
+        (store_size.next_multiple_of(align), align)
+    }
+
+    fn basic_layout(&self, ty: &PrimaryBasicType) -> Option<(u64, u64)> {
+        match ty {
+            PrimaryBasicType::Int(itype) => {
+                Some(Self::sized(itype.byte_size() as u64, self.max_scalar_align))
+            }
+            PrimaryBasicType::Float(ftype) => {
+                Some(Self::sized(ftype.byte_size() as u64, self.max_scalar_align))
+            }
+            PrimaryBasicType::Ptr(_) => Some((self.pointer_size, self.pointer_align)),
+            PrimaryBasicType::Ext(_) | PrimaryBasicType::Wildcard(_) => None,
+        }
+    }
+
+    fn primary_layout(&self, ty: &PrimaryType) -> Option<(u64, u64)> {
+        match ty {
+            PrimaryType::Int(itype) => self.basic_layout(&PrimaryBasicType::Int(*itype)),
+            PrimaryType::Float(ftype) => self.basic_layout(&PrimaryBasicType::Float(*ftype)),
+            PrimaryType::Ptr(ptr) => self.basic_layout(&PrimaryBasicType::Ptr(*ptr)),
+            PrimaryType::Vc(VcType {
+                ty,
+                size: VcSize::Fixed(num),
+            }) => {
+                let store_size = match ty {
+                    PrimaryBasicType::Ptr(_) => self.pointer_size * *num as u64,
+                    _ => (ty.size_bits()? as u64 * *num as u64).div_ceil(8),
+                };
+                Some(Self::sized(store_size, self.max_vector_align))
+            }
+            PrimaryType::Vc(_)
+            | PrimaryType::Ext(_)
+            | PrimaryType::Wildcard(_)
+            | PrimaryType::Lbl(_) => None,
+        }
+    }
+}
+
+impl Default for DataLayout {
+    fn default() -> Self {
+        Self::LP64
+    }
+}
+
+impl TypeRegistry {
+    fn layout_of(&self, typeref: Typeref, data_layout: &DataLayout) -> Option<(u64, u64)> {
+        self.layout_with_ancestors(typeref, data_layout, &mut Vec::new())
+    }
+
+    fn layout_with_ancestors(
+        &self,
+        typeref: Typeref,
+        data_layout: &DataLayout,
+        ancestors: &mut Vec<Typeref>,
+    ) -> Option<(u64, u64)> {
+        // Recursive types have no finite layout
+        if typeref.is_wildcard() || ancestors.contains(&typeref) {
+            return None;
+        }
+
+        let ty = self.get(typeref)?.clone();
+        ancestors.push(typeref);
+        let layout = match ty {
+            AnyType::Primary(primary) => data_layout.primary_layout(&primary),
+            AnyType::Array(ArrayType { ty, num_elements }) => self
+                .layout_with_ancestors(ty, data_layout, ancestors)
+                .map(|(size, align)| (size * num_elements as u64, align)),
+            AnyType::Struct(StructType {
+                element_types,
+                packed,
+            }) => {
+                let mut offset = 0u64;
+                let mut struct_align = 1u64;
+                for element in element_types {
+                    let (size, align) =
+                        self.layout_with_ancestors(element, data_layout, ancestors)?;
+                    if !packed {
+                        offset = offset.next_multiple_of(align);
+                        struct_align = struct_align.max(align);
+                    }
+                    offset += size;
+                }
+                Some((offset.next_multiple_of(struct_align), struct_align))
+            }
+        };
+        ancestors.pop();
+        layout
+    }
+
+    /// Size in bytes occupied by a value of type `typeref`, including trailing padding.
+    ///
+    /// This is the distance between two consecutive elements of an array of this type.
+    /// Returns `None` if the type is not in the registry, is recursive (see
+    /// [`Self::is_acyclic`]) or has no static layout (scalable vectors, extension types,
+    /// wildcards, labels).
+    pub fn size_of(&self, typeref: Typeref, data_layout: &DataLayout) -> Option<u64> {
+        self.layout_of(typeref, data_layout).map(|(size, _)| size)
+    }
+
+    /// Alignment in bytes required by a value of type `typeref`.
+    ///
+    /// Returns `None` under the same conditions as [`Self::size_of`].
+    pub fn align_of(&self, typeref: Typeref, data_layout: &DataLayout) -> Option<u64> {
+        self.layout_of(typeref, data_layout).map(|(_, align)| align)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::primary::{FType, IType, PtrType, VcType, WType};
+
+    use super::*;
+
+    #[test]
+    fn test_primary_layouts() {
+        let reg = TypeRegistry::new([0u8; 6]);
+        let dl = DataLayout::LP64;
+
+        let cases: [(AnyType, Option<(u64, u64)>); 9] = [
+            (IType::I1.into(), Some((1, 1))),
+            (IType::new(24).into(), Some((4, 4))),
+            (IType::I64.into(), Some((8, 8))),
+            (IType::new(256).into(), Some((32, 16))),
+            (FType::X86Fp80.into(), Some((16, 16))),
+            (PtrType.into(), Some((8, 8))),
+            (VcType::fixed(IType::I32, 3).into(), Some((16, 16))),
+            (VcType::fixed(PtrType, 2).into(), Some((16, 16))),
+            (
+                VcType {
+                    ty: IType::I32.into(),
+                    size: VcSize::Scalable(4),
+                }
+                .into(),
+                None,
+            ),
+        ];
+
+        for (ty, expected) in cases {
+            let typeref = reg.search_or_insert(ty);
+            assert_eq!(
+                reg.layout_of(typeref, &dl),
+                expected,
+                "{}",
+                reg.fmt(typeref)
+            );
+        }
+
+        let ptr = reg.search_or_insert(PtrType.into());
+        assert_eq!(reg.size_of(ptr, &DataLayout::ILP32), Some(4));
+        assert_eq!(reg.size_of(Typeref::new_wildcard(0), &dl), None);
+        assert_eq!(
+            reg.size_of(reg.search_or_insert(WType { id: 1 }.into()), &dl),
+            None
+        );
+    }
+
+    #[test]
+    fn test_struct_padding() {
+        let reg = TypeRegistry::new([0u8; 6]);
+        let dl = DataLayout::default();
+        let i8 = reg.search_or_insert(IType::I8.into());
+        let i16 = reg.search_or_insert(IType::I16.into());
+        let i64 = reg.search_or_insert(IType::I64.into());
+
+        // { i8, i64, i16 } -> 0, 8, 16 (+6 tail padding)
+        let unpacked = reg.search_or_insert(
+            StructType {
+                element_types: vec![i8, i64, i16],
+                packed: false,
+            }
+            .into(),
+        );
+        assert_eq!(reg.size_of(unpacked, &dl), Some(24));
+        assert_eq!(reg.align_of(unpacked, &dl), Some(8));
+
+        let packed = reg.search_or_insert(
+            StructType {
+                element_types: vec![i8, i64, i16],
+                packed: true,
+            }
+            .into(),
+        );
+        assert_eq!(reg.size_of(packed, &dl), Some(11));
+        assert_eq!(reg.align_of(packed, &dl), Some(1));
+
+        let empty = reg.search_or_insert(
+            StructType {
+                element_types: vec![],
+                packed: false,
+            }
+            .into(),
+        );
+        assert_eq!(reg.size_of(empty, &dl), Some(0));
+        assert_eq!(reg.align_of(empty, &dl), Some(1));
+    }
+
+    #[test]
+    fn test_nested_array() {
+        let reg = TypeRegistry::new([0u8; 6]);
+        let dl = DataLayout::default();
+        let i8 = reg.search_or_insert(IType::I8.into());
+        let i32 = reg.search_or_insert(IType::I32.into());
+
+        // { i32, i8 } occupies 8 bytes once its tail padding is included
+        let pair = reg.search_or_insert(
+            StructType {
+                element_types: vec![i32, i8],
+                packed: false,
+            }
+            .into(),
+        );
+        let row = reg.search_or_insert(
+            ArrayType {
+                ty: pair,
+                num_elements: 3,
+            }
+            .into(),
+        );
+        let matrix = reg.search_or_insert(
+            ArrayType {
+                ty: row,
+                num_elements: 2,
+            }
+            .into(),
+        );
+        assert_eq!(reg.size_of(row, &dl), Some(24));
+        assert_eq!(reg.size_of(matrix, &dl), Some(48));
+        assert_eq!(reg.align_of(matrix, &dl), Some(4));
+
+        // { i8, [2 x [3 x { i32, i8 }]] } pads the array to offset 4
+        let outer = reg.search_or_insert(
+            StructType {
+                element_types: vec![i8, matrix],
+                packed: false,
+            }
+            .into(),
+        );
+        assert_eq!(reg.size_of(outer, &dl), Some(52));
+    }
+}
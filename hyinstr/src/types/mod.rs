@@ -5,6 +5,7 @@
 //!
 //! - Primary types: primitive and vector types (see `primary.rs`).
 //! - Aggregate types: arrays and structures (see `aggregate.rs`).
+//! - Memory layout of types for a given target (see `layout.rs`).
 //! - A registry-backed [`AnyType`] wrapper and [`TypeRegistry`] which deduplicates
 //!   types and provides stable [`Typeref`] identifiers (UUID-based).
 //!
@@ -12,13 +13,14 @@
 //! that aggregate types can resolve their element types for human-friendly
 //! printing.
 use std::{
-    collections::BTreeMap,
-    hash::{DefaultHasher, Hash, Hasher},
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    hash::{Hash, Hasher},
     ops::Deref,
 };
 
 use auto_enums::auto_enum;
-use log::{debug, info};
+use log::{debug, info, warn};
 use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -26,12 +28,16 @@ use smallvec::{SmallVec, smallvec};
 use strum::{EnumIs, EnumTryAs};
 use uuid::{Timestamp, Uuid};
 
-use crate::types::{
-    aggregate::{ArrayType, StructType},
-    primary::{PrimaryType, WType},
+use crate::{
+    types::{
+        aggregate::{ArrayType, StructType},
+        primary::{PrimaryType, WType},
+    },
+    utils::StableHasher,
 };
 pub mod aggregate;
 pub mod checker;
+pub mod layout;
 pub mod primary;
 
 /// A stable reference to a type stored inside a `TypeRegistry`.
@@ -74,6 +80,12 @@ impl Typeref {
         Self(Uuid::new_v8(buf))
     }
 
+    /// Build a `Typeref` standing for a structural fingerprint (see
+    /// [`TypeRegistry::fingerprint`]). Such references are never stored in a registry.
+    pub(crate) fn from_fingerprint(fingerprint: u64) -> Self {
+        Self(Uuid::from_u64_pair(0, fingerprint))
+    }
+
     /// Retrieve the wildcard ID if this is a wildcard type.
     pub(crate) fn wildcard_id(&self) -> Option<u16> {
         if self.is_wildcard() {
@@ -149,25 +161,28 @@ impl From<StructType> for AnyType {
 }
 
 impl AnyType {
-    fn internal_fmt<U>(&self, ref_object: U) -> impl std::fmt::Display
+    fn internal_fmt<U>(&self, ref_object: U, ancestors: &[Uuid]) -> impl std::fmt::Display
     where
         U: Deref<Target = BTreeMap<Uuid, AnyType>> + Sized,
     {
-        struct AnyTypeFmt<'a, U> {
+        struct AnyTypeFmt<'a, 'b, U> {
             ty: &'a AnyType,
             ref_object: U,
+            ancestors: &'b [Uuid],
         }
 
-        impl<U: Deref<Target = BTreeMap<Uuid, AnyType>> + Sized> std::fmt::Display for AnyTypeFmt<'_, U> {
+        impl<U: Deref<Target = BTreeMap<Uuid, AnyType>> + Sized> std::fmt::Display
+            for AnyTypeFmt<'_, '_, U>
+        {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 match self.ty {
                     AnyType::Primary(primary_type) => primary_type.fmt(f),
-                    AnyType::Array(array_type) => {
-                        array_type.internal_fmt(self.ref_object.deref()).fmt(f)
-                    }
-                    AnyType::Struct(struct_type) => {
-                        struct_type.internal_fmt(self.ref_object.deref()).fmt(f)
-                    }
+                    AnyType::Array(array_type) => array_type
+                        .internal_fmt(self.ref_object.deref(), self.ancestors)
+                        .fmt(f),
+                    AnyType::Struct(struct_type) => struct_type
+                        .internal_fmt(self.ref_object.deref(), self.ancestors)
+                        .fmt(f),
                 }
             }
         }
@@ -175,6 +190,7 @@ impl AnyType {
         AnyTypeFmt {
             ty: self,
             ref_object,
+            ancestors,
         }
     }
 
@@ -209,7 +225,7 @@ impl AnyType {
     /// assert_eq!(format!("{}", t.fmt(&reg)), "i32");
     /// ```
     pub fn fmt<'a>(&'a self, registry: &'a TypeRegistry) -> impl std::fmt::Display {
-        self.internal_fmt(registry.array.read_recursive())
+        registry.fmt_type(Cow::Borrowed(self))
     }
 }
 
@@ -273,11 +289,7 @@ impl borsh::BorshDeserialize for TypeRegistry {
             array.insert(uuid, ty.clone());
 
             // Insert into inverse lookup
-            let h = {
-                let mut hasher = DefaultHasher::new();
-                ty.hash(&mut hasher);
-                hasher.finish()
-            };
+            let h = Self::hash_ty(&ty);
             if let Some(list) = inverse_lookup.get_mut(&h) {
                 list.push(uuid);
             } else {
@@ -296,7 +308,7 @@ impl borsh::BorshDeserialize for TypeRegistry {
 
 impl TypeRegistry {
     fn hash_ty(ty: &AnyType) -> u64 {
-        let mut hasher = DefaultHasher::new();
+        let mut hasher = StableHasher::new();
         ty.hash(&mut hasher);
         hasher.finish()
     }
@@ -323,6 +335,9 @@ impl TypeRegistry {
     /// Merge this registry with another, inserting all types from `other`
     /// into `self`. Returns a mapping from `other`'s `Typeref`s to `self`'s
     /// `Typeref`s.
+    ///
+    /// Types of `other` that (transitively) reference themselves or a type missing
+    /// from `other` cannot be inserted and are absent from the mapping.
     pub fn merge_with(&self, other: &TypeRegistry) -> BTreeMap<Typeref, Typeref> {
         let mut mapping = BTreeMap::new();
 
@@ -336,6 +351,7 @@ impl TypeRegistry {
         // to handle dependencies correctly. To achieve this, we first collect all types,
         // then insert all we can until none are left to insert.
         while !list_of_types.is_empty() {
+            let remaining = list_of_types.len();
             list_of_types.retain_mut(|(uuid, any_type)| {
                 let can_insert = any_type.iter_referenced_typerefs().all(|ref_typeref| {
                     mapping.contains_key(&ref_typeref) || ref_typeref.is_wildcard()
@@ -356,6 +372,14 @@ impl TypeRegistry {
                     true
                 }
             });
+
+            if list_of_types.len() == remaining {
+                warn!(
+                    "Skipped {} cyclic or dangling types while merging registries.",
+                    remaining
+                );
+                break;
+            }
         }
 
         mapping
@@ -471,18 +495,18 @@ impl TypeRegistry {
                     info!("Detected an hash collision on hash 0x{:016x}. The following types collided:\n{}",
                         h,
                         list.iter().map(|uuid| {
-                            format!(" - {} -> {}", uuid, array_lock.get(uuid).unwrap().internal_fmt(&*array_lock))
+                            format!(" - {} -> {}", uuid, array_lock.get(uuid).unwrap().internal_fmt(&*array_lock, &[*uuid]))
                         })
                         .collect::<Vec<_>>()
                         .join("\n"),
                     );
 
                     // Extra debug detail for the inverse lookup structure.
-                    debug!("Inverse lookup updated for hash 0x{:016x}: {:?} (type {})", h, list, ty.internal_fmt(&*array_lock));
+                    debug!("Inverse lookup updated for hash 0x{:016x}: {:?} (type {})", h, list, ty.internal_fmt(&*array_lock, &[]));
                     list.push(new_typeref);
                 } else {
                     // Normal insertion is a debug-level event.
-                    debug!("New type encountered {}. Registered with UUID {}.", ty.internal_fmt(&*array_lock), new_typeref);
+                    debug!("New type encountered {}. Registered with UUID {}.", ty.internal_fmt(&*array_lock, &[]), new_typeref);
                     inverse_lookup_lock.insert(h, smallvec![new_typeref]);
                 }
 
@@ -493,6 +517,137 @@ impl TypeRegistry {
         })
     }
 
+    /// Remove the type referenced by `typeref` from the registry and return it.
+    ///
+    /// Returns `None` if `typeref` is a wildcard or is not present in the registry. Inserting
+    /// an equal type afterwards allocates a fresh [`Typeref`].
+    ///
+    /// The registry does not track who references a type: removing a type that is still
+    /// used (by another type, a function signature, an instruction, ...) leaves those
+    /// [`Typeref`]s dangling. Ensuring that removed types are no longer referenced is the
+    /// caller's responsibility.
+    pub fn remove(&self, typeref: Typeref) -> Option<AnyType> {
+        if typeref.is_wildcard() {
+            return None;
+        }
+
+        // Lock, notice that the order is critical, always lock first database first
+        let mut array_lock = self.array.write();
+        let mut inverse_lookup_lock = self.inverse_lookup.write();
+
+        let ty = array_lock.remove(&typeref.0)?;
+        Self::unlink_inverse_lookup(&mut inverse_lookup_lock, Self::hash_ty(&ty), typeref.0);
+        debug!("Removed type {} from the registry.", typeref.0);
+        Some(ty)
+    }
+
+    /// Remove every type for which `keep` returns `false`.
+    ///
+    /// `keep` is evaluated without holding any lock, so it may query the registry. The
+    /// same dangling reference caveat as [`Self::remove`] applies.
+    pub fn retain(&self, mut keep: impl FnMut(Typeref) -> bool) {
+        let candidates: Vec<Uuid> = self.array.read().keys().copied().collect();
+        let to_remove: Vec<Uuid> = candidates
+            .into_iter()
+            .filter(|uuid| !keep(Typeref(*uuid)))
+            .collect();
+
+        // Lock, notice that the order is critical, always lock first database first
+        let mut array_lock = self.array.write();
+        let mut inverse_lookup_lock = self.inverse_lookup.write();
+        for uuid in to_remove {
+            if let Some(ty) = array_lock.remove(&uuid) {
+                Self::unlink_inverse_lookup(&mut inverse_lookup_lock, Self::hash_ty(&ty), uuid);
+            }
+        }
+    }
+
+    fn unlink_inverse_lookup(
+        inverse_lookup: &mut BTreeMap<u64, SmallVec<Uuid, 1>>,
+        h: u64,
+        uuid: Uuid,
+    ) {
+        if let Some(list) = inverse_lookup.get_mut(&h) {
+            list.retain(|candidate| *candidate != uuid);
+            if list.is_empty() {
+                inverse_lookup.remove(&h);
+            }
+        }
+    }
+
+    /// Compute a structural fingerprint of the type referenced by `typeref`.
+    ///
+    /// Unlike the [`Typeref`] itself, the fingerprint only depends on the structure
+    /// of the type, hence identical types registered in different registries share
+    /// the same fingerprint. Wildcards are fingerprinted by their identifier.
+    ///
+    /// Fingerprints are computed with [`StableHasher`], hence they do not change
+    /// across Rust releases or platforms and can be persisted. They only change
+    /// when the definition of the types themselves changes.
+    ///
+    /// Returns `None` if `typeref` (or any type it references) is not present in the
+    /// registry.
+    pub fn fingerprint(&self, typeref: Typeref) -> Option<u64> {
+        self.fingerprint_with_ancestors(typeref, &mut Vec::new())
+    }
+
+    fn fingerprint_with_ancestors(
+        &self,
+        typeref: Typeref,
+        ancestors: &mut Vec<Typeref>,
+    ) -> Option<u64> {
+        if let Some(wtype) = typeref.try_as_wildcard() {
+            return Some(Self::hash_ty(&PrimaryType::Wildcard(wtype).into()));
+        }
+        if ancestors.contains(&typeref) {
+            return None;
+        }
+
+        let mut ty = self.get(typeref)?.clone();
+        ancestors.push(typeref);
+        for child in ty.iter_referenced_typerefs_mut() {
+            *child = Typeref::from_fingerprint(self.fingerprint_with_ancestors(*child, ancestors)?);
+        }
+        ancestors.pop();
+        Some(Self::hash_ty(&ty))
+    }
+
+    /// Check that the type referenced by `typeref` does not (transitively) contain itself.
+    ///
+    /// Types inserted through [`Self::search_or_insert`] only reference previously
+    /// inserted types, but deserialized registries or dangling references (see
+    /// [`Self::remove`]) may form cycles. Layout computation ([`Self::size_of`]),
+    /// fingerprinting and formatting all detect such cycles.
+    pub fn is_acyclic(&self, typeref: Typeref) -> bool {
+        fn visit(
+            registry: &TypeRegistry,
+            typeref: Typeref,
+            visiting: &mut BTreeSet<Typeref>,
+            done: &mut BTreeSet<Typeref>,
+        ) -> bool {
+            if typeref.is_wildcard() || done.contains(&typeref) {
+                return true;
+            }
+            if !visiting.insert(typeref) {
+                return false;
+            }
+
+            let children: Vec<Typeref> = match registry.get(typeref) {
+                Some(ty) => ty.iter_referenced_typerefs().collect(),
+                None => vec![],
+            };
+            let acyclic = children
+                .into_iter()
+                .all(|child| visit(registry, child, visiting, done));
+
+            visiting.remove(&typeref);
+            done.insert(typeref);
+            acyclic
+        }
+
+        visit(self, typeref, &mut BTreeSet::new(), &mut BTreeSet::new())
+    }
+
     /// Format a given `Typeref` using this registry.
     pub fn fmt(&self, typeref: Typeref) -> impl std::fmt::Display {
         struct Fmt<'a> {
@@ -503,7 +658,9 @@ impl TypeRegistry {
         impl<'a> std::fmt::Display for Fmt<'a> {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 match self.registry.get(self.typeref) {
-                    Some(ty_guard) => ty_guard.fmt(self.registry).fmt(f),
+                    Some(ty_guard) => ty_guard
+                        .internal_fmt(self.registry.array.read_recursive(), &[self.typeref.0])
+                        .fmt(f),
                     None => write!(f, "<unknown type {}>", self.typeref.0),
                 }
             }
@@ -515,6 +672,37 @@ impl TypeRegistry {
         }
     }
 
+    /// Build a formatting helper for a type that may be stored in the registry.
+    ///
+    /// If `ty` is registered, its own UUID is treated as an ancestor such that
+    /// self-references are printed as `<recursive>` right away.
+    pub(crate) fn fmt_type<'a>(&'a self, ty: Cow<'a, AnyType>) -> impl std::fmt::Display + 'a {
+        struct Fmt<'a> {
+            registry: &'a TypeRegistry,
+            ty: Cow<'a, AnyType>,
+        }
+
+        impl std::fmt::Display for Fmt<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let array_lock = self.registry.array.read_recursive();
+                let root = self
+                    .registry
+                    .inverse_lookup
+                    .read_recursive()
+                    .get(&TypeRegistry::hash_ty(&self.ty))
+                    .and_then(|uuids| {
+                        uuids
+                            .iter()
+                            .copied()
+                            .find(|uuid| array_lock.get(uuid) == Some(&*self.ty))
+                    });
+                self.ty.internal_fmt(&*array_lock, root.as_slice()).fmt(f)
+            }
+        }
+
+        Fmt { registry: self, ty }
+    }
+
     /// Number of types stored in the registry. Should be used for debugging
     /// because of concurrency.
     pub fn len(&self) -> usize {
@@ -594,6 +782,132 @@ mod tests {
         let _ = reg.get(wildcard_typeref);
     }
 
+    #[test]
+    fn test_stable_hasher() {
+        // FNV-1a reference vectors
+        let hash = |bytes: &[u8]| {
+            let mut hasher = StableHasher::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash(b"foobar"), 0x85944171f73967e8);
+
+        // Integers are hashed as little-endian bytes, whatever the platform
+        let mut hasher = StableHasher::new();
+        hasher.write_usize(0x61);
+        assert_eq!(hasher.finish(), hash(&0x61u64.to_le_bytes()));
+    }
+
+    #[test]
+    fn test_registry_remove() {
+        let reg = TypeRegistry::new([0u8; 6]);
+        let typeref_i32 = reg.search_or_insert(IType::I32.into());
+        let typeref_i64 = reg.search_or_insert(IType::I64.into());
+        let h = TypeRegistry::hash_ty(&IType::I32.into());
+        assert_eq!(reg.inverse_lookup.read()[&h].len(), 1);
+
+        assert_eq!(reg.remove(typeref_i32), Some(IType::I32.into()));
+        assert_eq!(reg.remove(typeref_i32), None);
+        assert!(reg.get(typeref_i32).is_none());
+        assert!(!reg.inverse_lookup.read().contains_key(&h));
+        assert_eq!(reg.len(), 1);
+
+        // Re-inserting allocates a fresh typeref
+        let reinserted = reg.search_or_insert(IType::I32.into());
+        assert_ne!(reinserted, typeref_i32);
+        assert_eq!(reg.get(reinserted).as_deref(), Some(&IType::I32.into()));
+        assert_eq!(reg.get(typeref_i64).as_deref(), Some(&IType::I64.into()));
+
+        // Wildcards are never stored
+        assert_eq!(reg.remove(Typeref::new_wildcard(3)), None);
+    }
+
+    #[test]
+    fn test_registry_retain() {
+        let reg = TypeRegistry::new([0u8; 6]);
+        let typeref_i8 = reg.search_or_insert(IType::I8.into());
+        let typeref_i32 = reg.search_or_insert(IType::I32.into());
+        let typeref_i64 = reg.search_or_insert(IType::I64.into());
+
+        // The predicate may query the registry
+        reg.retain(|typeref| reg.get(typeref).as_deref() != Some(&IType::I32.into()));
+        assert_eq!(reg.len(), 2);
+        assert!(reg.get(typeref_i32).is_none());
+        assert!(reg.get(typeref_i8).is_some());
+        assert!(reg.get(typeref_i64).is_some());
+        assert_eq!(reg.inverse_lookup.read().len(), 2);
+    }
+
+    #[test]
+    fn test_registry_detects_cyclic_types() {
+        use crate::types::layout::DataLayout;
+
+        let reg = TypeRegistry::new([0u8; 6]);
+        let i32_typeref = reg.search_or_insert(IType::I32.into());
+        let node = reg.search_or_insert(
+            StructType {
+                element_types: vec![i32_typeref],
+                packed: false,
+            }
+            .into(),
+        );
+        let array = reg.search_or_insert(
+            ArrayType {
+                ty: node,
+                num_elements: 2,
+            }
+            .into(),
+        );
+        let wrapper = reg.search_or_insert(
+            StructType {
+                element_types: vec![i32_typeref, array],
+                packed: false,
+            }
+            .into(),
+        );
+        assert!(reg.is_acyclic(wrapper));
+        assert!(reg.fingerprint(wrapper).is_some());
+
+        // Make `node` refer to itself: {i32, node}, as a deserialized registry could
+        let mut array_lock = reg.array.write();
+        let mut inverse_lookup = reg.inverse_lookup.write();
+        let ty = array_lock.get_mut(&node.0).unwrap();
+        TypeRegistry::unlink_inverse_lookup(&mut inverse_lookup, TypeRegistry::hash_ty(ty), node.0);
+        if let AnyType::Struct(st) = ty {
+            st.element_types.push(node);
+        }
+        inverse_lookup
+            .entry(TypeRegistry::hash_ty(ty))
+            .or_default()
+            .push(node.0);
+        drop((array_lock, inverse_lookup));
+
+        assert!(reg.is_acyclic(i32_typeref));
+        assert!(!reg.is_acyclic(node));
+        assert!(!reg.is_acyclic(array));
+        assert!(!reg.is_acyclic(wrapper));
+
+        let dl = DataLayout::default();
+        assert_eq!(reg.size_of(node, &dl), None);
+        assert_eq!(reg.align_of(wrapper, &dl), None);
+        assert_eq!(reg.fingerprint(wrapper), None);
+        assert_eq!(reg.fmt(node).to_string(), "{i32, <recursive>}");
+        assert_eq!(
+            reg.get(node).unwrap().clone().fmt(&reg).to_string(),
+            "{i32, <recursive>}"
+        );
+        assert!(reg.fmt(wrapper).to_string().contains("<recursive>"));
+
+        // Merging skips the cyclic types instead of looping forever
+        let other = TypeRegistry::new([1u8; 6]);
+        let mapping = other.merge_with(&reg);
+        assert!(mapping.contains_key(&i32_typeref));
+        assert!(!mapping.contains_key(&node));
+        assert!(!mapping.contains_key(&wrapper));
+    }
+
     #[test]
     fn test_registry_on_complex_types() {
         let reg = TypeRegistry::new([0u8; 6]);
@@ -183,6 +183,18 @@ impl FType {
         }
     }
 
+    /// Returns the number of bits of the floating-point type.
+    #[inline]
+    pub fn num_bits(&self) -> u32 {
+        match self {
+            FType::Fp16 | FType::Bf16 => 16,
+            FType::Fp32 => 32,
+            FType::Fp64 => 64,
+            FType::Fp128 | FType::PPCFp128 => 128,
+            FType::X86Fp80 => 80,
+        }
+    }
+
     /// Returns the number of bytes required to store the floating-point type.
     #[inline]
     pub fn byte_size(&self) -> u32 {
@@ -360,6 +372,22 @@ impl std::fmt::Display for PrimaryBasicType {
     }
 }
 
+impl PrimaryBasicType {
+    /// Returns the size in bits of the type.
+    ///
+    /// Returns `None` when the size is target-dependent (pointers, extension types)
+    /// or unknown (wildcards).
+    pub fn size_bits(&self) -> Option<u32> {
+        match self {
+            PrimaryBasicType::Int(itype) => Some(itype.num_bits()),
+            PrimaryBasicType::Float(ftype) => Some(ftype.num_bits()),
+            PrimaryBasicType::Ext(_) | PrimaryBasicType::Ptr(_) | PrimaryBasicType::Wildcard(_) => {
+                None
+            }
+        }
+    }
+}
+
 /// Size of a vector type, either fixed or scalable.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -535,3 +563,25 @@ impl std::fmt::Display for PrimaryType {
         }
     }
 }
+
+impl PrimaryType {
+    /// Returns the size in bits of the type.
+    ///
+    /// Returns `None` when the size is target-dependent (pointers, extension types,
+    /// scalable vectors), unknown (wildcards) or meaningless (labels).
+    pub fn size_bits(&self) -> Option<u32> {
+        match self {
+            PrimaryType::Int(itype) => Some(itype.num_bits()),
+            PrimaryType::Float(ftype) => Some(ftype.num_bits()),
+            PrimaryType::Vc(VcType {
+                ty,
+                size: VcSize::Fixed(num),
+            }) => ty.size_bits().map(|bits| bits * *num as u32),
+            PrimaryType::Vc(_)
+            | PrimaryType::Ext(_)
+            | PrimaryType::Ptr(_)
+            | PrimaryType::Wildcard(_)
+            | PrimaryType::Lbl(_) => None,
+        }
+    }
+}
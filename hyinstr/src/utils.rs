@@ -1,4 +1,6 @@
 //! Shared utilities and error types used across the hyinstr crate.
+use std::hash::Hasher;
+
 use strum::{EnumIs, EnumTryAs};
 use thiserror::Error;
 use uuid::Uuid;
@@ -21,6 +23,82 @@ pub struct ParserError {
     pub message: String,
 }
 
+/// 64-bit FNV-1a hasher producing values that are stable across Rust releases and
+/// platforms.
+///
+/// Unlike [`std::hash::DefaultHasher`], whose algorithm is unspecified, the output only
+/// depends on the sequence of values fed by the [`std::hash::Hash`] implementations.
+/// Integers are hashed in little-endian order and `usize`/`isize` are widened to 64 bits.
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    pub fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+}
+
 #[derive(Debug, EnumIs, EnumTryAs, Error)]
 /// Generic error enumeration surfaced by hyinstr operations.
 pub enum Error {
@@ -42,12 +120,36 @@ pub enum Error {
     )]
     UndefinedSSAName { undefined: Name },
 
+    /// An operand refers to a name whose definition does not dominate the use.
+    #[error(
+        "The name `{name}` is used in basic block `{block}` on a path where it has not been defined yet."
+    )]
+    UseBeforeDef { name: Name, block: Label },
+
     /// Provided internal function is not defined within the module.
     #[error(
         "An instruction of function `{function}` refers to an internal function referenced by `{undefined}` that is not defined within the module."
     )]
     UndefinedInternalFunction { function: String, undefined: Uuid },
 
+    /// The referenced instruction is not an `invoke` of an internal function.
+    #[error(
+        "The instruction {index} of basic block `{block}` in function `{function}` is not an invocation of an internal function."
+    )]
+    InvalidCallSite {
+        function: String,
+        block: Label,
+        index: u32,
+    },
+
+    /// The call cannot be inlined.
+    #[error("The call to `{callee}` in function `{caller}` cannot be inlined: {reason}.")]
+    InlineUnsupported {
+        caller: String,
+        callee: String,
+        reason: String,
+    },
+
     /// Provided external function is not defined within the module.
     #[error(
         "An instruction of function `{function}` refers to an external function referenced by `{undefined}` that is not defined within the module."
@@ -76,12 +178,53 @@ pub enum Error {
     )]
     PhiNotFirstInstruction { block: Label },
 
+    /// The incoming blocks of a phi instruction do not match the predecessors of its block.
+    #[error(
+        "A phi instruction of basic block `{block}` in function `{function}` does not match the predecessors of the block: missing incoming values from {missing:?}, unexpected incoming values from {unexpected:?}."
+    )]
+    PhiPredecessorMismatch {
+        function: String,
+        block: Label,
+        missing: Vec<Label>,
+        unexpected: Vec<Label>,
+    },
+
+    /// The basic block cannot be reached from the entry block.
+    #[error(
+        "The basic block `{label}` in function `{function}` cannot be reached from the entry block."
+    )]
+    UnreachableBlock { function: String, label: Label },
+
+    /// An instruction does not have the expected number of operands.
+    #[error(
+        "Instruction `{op}` expects {expected} operand(s) (at least, for variadic instructions), but {found} were provided."
+    )]
+    ArityMismatch {
+        op: String,
+        expected: usize,
+        found: usize,
+    },
+
     /// The basic block referenced cannot be found within the function.
     #[error(
         "The basic block `{label}` referenced in function `{function}` is not defined within the function."
     )]
     UndefinedBasicBlock { function: String, label: Label },
 
+    /// The entry block of a function cannot be removed.
+    #[error("The entry basic block of function `{function}` cannot be removed.")]
+    EntryBlockRemoval { function: String },
+
+    /// The basic block is still the target of another block's terminator.
+    #[error(
+        "The basic block `{label}` in function `{function}` cannot be removed as it is still targeted by basic block `{predecessor}`."
+    )]
+    BlockStillReferenced {
+        function: String,
+        label: Label,
+        predecessor: Label,
+    },
+
     /// Meta instructions are not allowed in this context.
     #[error(
         "Meta instructions are only available internally for properties and attributes constructions. They SHOULD NOT appear in regular instructions. Function `{function}` contains a meta-instruction `{instruction}`."
@@ -142,6 +285,12 @@ pub enum Error {
         max: usize,
     },
 
+    /// A regular instruction depends on the result of a meta-instruction.
+    #[error(
+        "The result `{name}` of a meta-instruction in function `{function}` is used by a non-meta instruction. Meta-instructions cannot be stripped without leaving undefined references."
+    )]
+    MetaResultInUse { function: String, name: Name },
+
     /// A basic block with the given label already exists in the function.
     #[error("A basic block with label `{0}` already exists in the function.")]
     BlockLabelAlreadyExists(Label),
@@ -2,28 +2,32 @@ use std::sync::Arc;
 use std::{
     collections::{BTreeMap, BTreeSet},
     fs,
+    hash::{DefaultHasher, Hash, Hasher},
 };
 
 use hyinstr::{
     consts::AnyConst,
     modules::{
-        self, BasicBlock, Function, Module,
+        self, BasicBlock, CallingConvention, Function, InstructionRef, Module, NaturalLoop,
         instructions::{
-            HyInstr, Instruction,
+            HyInstr, HyInstrOp, Instruction,
             int::{IAdd, ICmp, ICmpVariant, OverflowSignednessPolicy},
+            mem::MGetElementPtr,
+            meta::MetaAssert,
             misc::{Invoke, Phi},
         },
         operand::{Label, Name, Operand},
         parser::{extend_module_from_path, extend_module_from_string},
-        symbol::{FunctionPointer, FunctionPointerType},
-        terminator::{Branch, HyTerminator, Jump, Ret},
+        symbol::{ExternalFunction, FunctionPointer, FunctionPointerType},
+        terminator::{Branch, HyTerminator, Jump, Ret, Trap},
     },
     types::{
         TypeRegistry, Typeref,
-        primary::{IType, WType},
+        primary::{IType, PrimaryBasicType, PtrType, WType},
     },
     utils::Error,
 };
+use strum::IntoEnumIterator;
 use uuid::Uuid;
 
 fn registry() -> TypeRegistry {
@@ -92,6 +96,95 @@ fn simple_ok_function(reg: &TypeRegistry) -> Function {
     )
 }
 
+/// `entry -> {1, 2} -> 3`, branching on the `i1` parameter.
+fn diamond_function(reg: &TypeRegistry) -> Function {
+    function(
+        "diamond",
+        vec![(Name(0), i1(reg))],
+        vec![
+            block(
+                Label::NIL,
+                vec![],
+                HyTerminator::from(Branch {
+                    cond: Operand::Reg(Name(0)),
+                    target_true: Label(1),
+                    target_false: Label(2),
+                }),
+            ),
+            block(
+                Label(1),
+                vec![],
+                HyTerminator::from(Jump { target: Label(3) }),
+            ),
+            block(
+                Label(2),
+                vec![],
+                HyTerminator::from(Jump { target: Label(3) }),
+            ),
+            block(Label(3), vec![], HyTerminator::from(Ret { value: None })),
+        ],
+        None,
+        BTreeSet::new(),
+        false,
+    )
+}
+
+/// `entry -> 1 -> {2, 3}` where `2` jumps back to the loop header `1` and `3` traps.
+fn loop_function(reg: &TypeRegistry) -> Function {
+    function(
+        "loop",
+        vec![(Name(0), i1(reg))],
+        vec![
+            block(
+                Label::NIL,
+                vec![],
+                HyTerminator::from(Jump { target: Label(1) }),
+            ),
+            block(
+                Label(1),
+                vec![],
+                HyTerminator::from(Branch {
+                    cond: Operand::Reg(Name(0)),
+                    target_true: Label(2),
+                    target_false: Label(3),
+                }),
+            ),
+            block(
+                Label(2),
+                vec![],
+                HyTerminator::from(Jump { target: Label(1) }),
+            ),
+            block(Label(3), vec![], HyTerminator::from(Trap)),
+        ],
+        None,
+        BTreeSet::new(),
+        false,
+    )
+}
+
+/// Arbitrary SSA renumbering used to check that analyses ignore SSA names.
+fn shift(name: Name) -> Name {
+    Name(name.0 * 3 + 7)
+}
+
+/// Copy of `func` with every SSA name renumbered through [`shift`].
+fn shift_ssa_names(func: &Function) -> Function {
+    let mut renamed = func.clone();
+    for (name, _) in renamed.params.iter_mut() {
+        *name = shift(*name);
+    }
+    for bb in renamed.body.values_mut() {
+        for instr in bb.instructions.iter_mut() {
+            if let Some(dest) = instr.destination() {
+                instr.set_destination(shift(dest));
+            }
+            instr.remap_operands(|name| Some(shift(name)));
+        }
+        bb.terminator.remap_operands(|name| Some(shift(name)));
+    }
+    renamed
+}
+
 #[test]
 fn function_verify_simple_passes() {
     let reg = registry();
@@ -627,11 +720,7 @@ fn parser_handles_imports_with_extend_module_from_path() {
     fs::remove_dir_all(temp_dir).unwrap();
 }
 
-#[test]
-fn parser_extended_factorial_example_resolves_calls() {
-    let reg = registry();
-    let mut module = Module::default();
-    let ir = r#"
+const FACTORIAL_IR: &str = r#"
 define i32 factorial ( %n: i32 ) {
 entry:
    %cmp1: i1 = icmp.eq %n, i32 0
@@ -678,7 +767,12 @@ entry:
 }
 "#;
 
-    extend_module_from_string(&mut module, &reg, ir).unwrap();
+#[test]
+fn parser_extended_factorial_example_resolves_calls() {
+    let reg = registry();
+    let mut module = Module::default();
+
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
 
     let factorial_uuid = module
         .find_internal_function_uuid_by_name("factorial")
@@ -776,6 +870,259 @@ fn parser_reports_unresolved_external_function() {
     );
 }
 
+#[test]
+fn parser_parses_load_and_store() {
+    use hyinstr::modules::instructions::mem::{MLoad, MStore, MemoryOrdering};
+
+    let reg = registry();
+    let mut module = Module::default();
+    let src = r#"
+        define i32 copy(%src: ptr, %dst: ptr) {
+        entry:
+            %v: i32 = load.acquire %src, align 4
+            %w: i32 = load %src, volatile
+            store.release %dst, %v, align 8, volatile
+            store %dst, i32 7
+            ret %w
+        }
+    "#;
+
+    extend_module_from_string(&mut module, &reg, src).unwrap();
+    let uuid = module.find_internal_function_uuid_by_name("copy").unwrap();
+    let func = module.get_internal_function_by_uuid(uuid).unwrap();
+    let instrs = &func.body[&Label::NIL].instructions;
+
+    assert_eq!(
+        instrs[0],
+        HyInstr::MLoad(MLoad {
+            dest: Name(2),
+            ty: i32(&reg),
+            addr: Operand::Reg(Name(0)),
+            alignement: Some(4),
+            ordering: Some(MemoryOrdering::Acq),
+            volatile: false,
+        })
+    );
+    assert!(matches!(
+        &instrs[1],
+        HyInstr::MLoad(MLoad {
+            alignement: None,
+            ordering: None,
+            volatile: true,
+            ..
+        })
+    ));
+    assert_eq!(
+        instrs[2],
+        HyInstr::MStore(MStore {
+            addr: Operand::Reg(Name(1)),
+            value: Operand::Reg(Name(2)),
+            alignement: Some(8),
+            ordering: Some(MemoryOrdering::Rel),
+            volatile: true,
+        })
+    );
+    assert!(matches!(
+        &instrs[3],
+        HyInstr::MStore(MStore {
+            alignement: None,
+            ordering: None,
+            volatile: false,
+            value: Operand::Imm(_),
+            ..
+        })
+    ));
+
+    let printed = module.to_source(&reg);
+    let mut reparsed = Module::default();
+    extend_module_from_string(&mut reparsed, &reg, &printed).unwrap();
+    assert!(module.diff(&reparsed).is_empty(), "{printed}");
+}
+
+#[test]
+fn parser_parses_invoke_with_calling_convention() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+    let src = r#"
+        define i32 caller(%arg: i32) {
+        entry:
+            %a: i32 = invoke ptr factorial, %arg
+            %b: i32 = invoke fastcc ptr factorial, %a
+            invoke coldcc ptr factorial, i32 3
+            ret %b
+        }
+    "#;
+    extend_module_from_string(&mut module, &reg, src).unwrap();
+
+    let factorial = module
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+    let uuid = module
+        .find_internal_function_uuid_by_name("caller")
+        .unwrap();
+    let func = module.get_internal_function_by_uuid(uuid).unwrap();
+    let invokes: Vec<&Invoke> = func.body[&Label::NIL]
+        .instructions
+        .iter()
+        .map(|instr| instr.try_as_invoke_ref().unwrap())
+        .collect();
+
+    assert_eq!(invokes.len(), 3);
+    for invoke in &invokes {
+        assert_eq!(
+            invoke.function,
+            Operand::Imm(AnyConst::FuncPtr(FunctionPointer::Internal(factorial)))
+        );
+        assert_eq!(invoke.args.len(), 1);
+    }
+    assert_eq!(invokes[0].cconv, None);
+    assert_eq!(invokes[0].args[0], Operand::Reg(Name(0)));
+    assert_eq!(invokes[0].dest, Some(Name(1)));
+    assert_eq!(invokes[1].cconv, Some(CallingConvention::FastC));
+    assert_eq!(invokes[2].cconv, Some(CallingConvention::ColdC));
+    assert_eq!(invokes[2].dest, None);
+
+    let printed = module.to_source(&reg);
+    let mut reparsed = Module::default();
+    extend_module_from_string(&mut reparsed, &reg, &printed).unwrap();
+    assert!(module.diff(&reparsed).is_empty(), "{printed}");
+}
+
+#[test]
+fn parser_rejects_calling_convention_outside_invoke() {
+    let reg = registry();
+    let mut module = Module::default();
+    let src = r#"
+        define i32 bad(%a: i32) {
+        entry:
+            %b: i32 = iadd.wrap fastcc %a, %a
+            ret %b
+        }
+    "#;
+    assert!(extend_module_from_string(&mut module, &reg, src).is_err());
+}
+
+#[test]
+fn parser_resolves_forward_references_into_call_graph() {
+    let reg = registry();
+    let mut module = Module::default();
+    let src = r#"
+define i32 outer(%x: i32) {
+entry:
+    %a: i32 = invoke ptr middle, %x
+    %b: i32 = invoke ptr inner, %a
+    ret %b
+}
+
+define i32 middle(%x: i32) {
+entry:
+    %a: i32 = invoke ptr inner, %x
+    ret %a
+}
+
+define i32 inner(%x: i32) {
+entry:
+    ret %x
+}
+"#;
+    extend_module_from_string(&mut module, &reg, src).unwrap();
+    assert_eq!(module.functions.len(), 3);
+
+    let uuid_of = |name: &str| module.find_internal_function_uuid_by_name(name).unwrap();
+    let callees = |name: &str| -> Vec<Uuid> {
+        let func = module.get_internal_function_by_uuid(uuid_of(name)).unwrap();
+        func.body[&Label::NIL]
+            .instructions
+            .iter()
+            .filter_map(|instr| instr.try_as_invoke_ref())
+            .map(|invoke| match &invoke.function {
+                Operand::Imm(AnyConst::FuncPtr(FunctionPointer::Internal(uuid))) => *uuid,
+                other => panic!("unexpected callee operand {other:?}"),
+            })
+            .collect()
+    };
+
+    assert_eq!(callees("outer"), vec![uuid_of("middle"), uuid_of("inner")]);
+    assert_eq!(callees("middle"), vec![uuid_of("inner")]);
+    assert!(callees("inner").is_empty());
+}
+
+#[test]
+fn parser_declares_external_functions() {
+    let reg = registry();
+    let mut module = Module::default();
+    let source = r#"
+declare ptr external malloc(i32)
+declare void fastcc external free(ptr)
+
+define void caller() {
+entry:
+    %p: ptr = invoke ptr external malloc, i32 16
+    invoke ptr external free, %p
+    ret void
+}
+"#;
+
+    extend_module_from_string(&mut module, &reg, source).unwrap();
+    module.verify().unwrap();
+    assert_eq!(module.external_functions.len(), 2);
+
+    let malloc = match module.find_function_uuid_by_name("malloc", FunctionPointerType::External) {
+        Some(FunctionPointer::External(uuid)) => module.external_functions[&uuid].clone(),
+        other => panic!("malloc not declared: {other:?}"),
+    };
+    let ptr = reg.search_or_insert(PrimaryBasicType::Ptr(PtrType).into());
+    assert_eq!(malloc.cconv, CallingConvention::C);
+    assert_eq!(malloc.param_types, vec![i32(&reg)]);
+    assert_eq!(malloc.return_type, Some(ptr));
+
+    let free = module
+        .external_functions
+        .values()
+        .find(|f| f.name == "free")
+        .unwrap();
+    assert_eq!(free.cconv, CallingConvention::FastC);
+    assert_eq!(free.return_type, None);
+
+    let uuid = module
+        .find_internal_function_uuid_by_name("caller")
+        .unwrap();
+    let func = module.get_internal_function_by_uuid(uuid).unwrap();
+    let callees: Vec<_> = func.body[&Label::NIL]
+        .instructions
+        .iter()
+        .filter_map(|instr| instr.try_as_invoke_ref())
+        .map(|invoke| invoke.function.clone())
+        .collect();
+    assert_eq!(
+        callees,
+        vec![
+            Operand::Imm(AnyConst::FuncPtr(FunctionPointer::External(malloc.uuid))),
+            Operand::Imm(AnyConst::FuncPtr(FunctionPointer::External(free.uuid))),
+        ]
+    );
+
+    let printed = module.to_source(&reg);
+    let mut reparsed = Module::default();
+    extend_module_from_string(&mut reparsed, &reg, &printed).unwrap();
+    assert!(module.diff(&reparsed).is_empty(), "{printed}");
+    assert_eq!(reparsed.external_functions.len(), 2);
+}
+
+#[test]
+fn parser_rejects_duplicate_external_declaration() {
+    let reg = registry();
+    let mut module = Module::default();
+    let source = r#"
+declare void external abort()
+declare void abort()
+"#;
+
+    let err = extend_module_from_string(&mut module, &reg, source).unwrap_err();
+    assert!(matches!(err, Error::FunctionAlreadyExists { name } if name == "abort"));
+}
+
 #[test]
 fn parser_parses_meta_analysis_stat_termination_variant() {
     use hyinstr::analysis::{AnalysisStatistic, TerminationScope};
@@ -844,3 +1191,1769 @@ fn parser_parses_meta_analysis_stat_instruction_count_operand() {
         panic!("expected MetaAnalysisStat as first instruction");
     }
 }
+
+#[test]
+fn instruction_substitute_operand_replaces_register_with_immediate() {
+    let reg = registry();
+    let ty = i32(&reg);
+    let mut add = HyInstr::from(IAdd {
+        dest: Name(2),
+        ty,
+        lhs: Operand::Reg(Name(0)),
+        rhs: Operand::Reg(Name(0)),
+        variant: OverflowSignednessPolicy::Wrap,
+    });
+
+    add.substitute_operand(Name(0), Operand::Imm(7u32.into()));
+    let operands: Vec<&Operand> = add.operands().collect();
+    assert_eq!(
+        operands,
+        vec![&Operand::Imm(7u32.into()), &Operand::Imm(7u32.into())]
+    );
+    assert_eq!(add.destination(), Some(Name(2)));
+
+    // Function-level sweep also rewrites terminators
+    let mut func = simple_ok_function(&reg);
+    func.substitute_operand(Name(1), Operand::Imm(3u32.into()));
+    if let HyTerminator::Ret(ret) = &func.body[&Label::NIL].terminator {
+        assert_eq!(ret.value, Some(Operand::Imm(3u32.into())));
+    } else {
+        panic!("ret missing");
+    }
+}
+
+#[test]
+fn module_functions_by_name_is_stable() {
+    let reg = registry();
+    let names = ["zeta", "alpha", "mid", "alpha"];
+
+    let mut module = Module::default();
+    for (idx, name) in names.iter().enumerate() {
+        let mut func = simple_ok_function(&reg);
+        func.name = Some(name.to_string());
+        // UUIDs deliberately ordered opposite to names
+        func.uuid = Uuid::from_u128(100 - idx as u128);
+        module.functions.insert(func.uuid, Arc::new(func));
+    }
+
+    let order: Vec<(&str, Uuid)> = module
+        .functions_by_name()
+        .map(|f| (f.name.as_deref().unwrap(), f.uuid))
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            ("alpha", Uuid::from_u128(97)),
+            ("alpha", Uuid::from_u128(99)),
+            ("mid", Uuid::from_u128(98)),
+            ("zeta", Uuid::from_u128(100)),
+        ]
+    );
+}
+
+#[test]
+fn function_merge_identical_blocks_collapses_duplicates() {
+    let reg = registry();
+    let ty = i32(&reg);
+
+    let entry = block(
+        Label::NIL,
+        vec![HyInstr::from(ICmp {
+            dest: Name(1),
+            ty: i1(&reg),
+            lhs: Operand::Reg(Name(0)),
+            rhs: Operand::Imm(0u32.into()),
+            variant: ICmpVariant::Eq,
+        })],
+        HyTerminator::from(Branch {
+            cond: Operand::Reg(Name(1)),
+            target_true: Label(1),
+            target_false: Label(2),
+        }),
+    );
+    let l1 = block(
+        Label(1),
+        vec![],
+        HyTerminator::from(Jump { target: Label(3) }),
+    );
+    let l2 = block(
+        Label(2),
+        vec![],
+        HyTerminator::from(Jump { target: Label(3) }),
+    );
+    let l3 = block(
+        Label(3),
+        vec![HyInstr::from(Phi {
+            dest: Name(2),
+            ty,
+            values: vec![
+                (Operand::Imm(5u32.into()), Label(1)),
+                (Operand::Imm(5u32.into()), Label(2)),
+            ],
+        })],
+        HyTerminator::from(Ret {
+            value: Some(Operand::Reg(Name(2))),
+        }),
+    );
+
+    let mut func = function(
+        "merge",
+        vec![(Name(0), ty)],
+        vec![entry, l1, l2, l3],
+        Some(ty),
+        BTreeSet::new(),
+        false,
+    );
+    func.verify().unwrap();
+
+    assert_eq!(func.merge_identical_blocks(), 1);
+    assert_eq!(func.body.len(), 3);
+    assert!(!func.body.contains_key(&Label(2)));
+    func.verify().unwrap();
+
+    let targets: Vec<Label> = func.body[&Label::NIL]
+        .terminator
+        .iter_targets()
+        .map(|(label, _)| label)
+        .collect();
+    assert_eq!(targets, vec![Label(1), Label(1)]);
+
+    if let HyInstr::Phi(phi) = &func.body[&Label(3)].instructions[0] {
+        assert_eq!(phi.values, vec![(Operand::Imm(5u32.into()), Label(1))]);
+    } else {
+        panic!("expected phi");
+    }
+
+    // Nothing left to merge
+    assert_eq!(func.merge_identical_blocks(), 0);
+}
+
+#[test]
+fn function_merge_identical_blocks_respects_phi_values() {
+    let reg = registry();
+    let ty = i32(&reg);
+
+    let mut func = function(
+        "no_merge",
+        vec![(Name(0), i1(&reg))],
+        vec![
+            block(
+                Label::NIL,
+                vec![],
+                HyTerminator::from(Branch {
+                    cond: Operand::Reg(Name(0)),
+                    target_true: Label(1),
+                    target_false: Label(2),
+                }),
+            ),
+            block(
+                Label(1),
+                vec![],
+                HyTerminator::from(Jump { target: Label(3) }),
+            ),
+            block(
+                Label(2),
+                vec![],
+                HyTerminator::from(Jump { target: Label(3) }),
+            ),
+            block(
+                Label(3),
+                vec![HyInstr::from(Phi {
+                    dest: Name(1),
+                    ty,
+                    values: vec![
+                        (Operand::Imm(1u32.into()), Label(1)),
+                        (Operand::Imm(2u32.into()), Label(2)),
+                    ],
+                })],
+                HyTerminator::from(Ret {
+                    value: Some(Operand::Reg(Name(1))),
+                }),
+            ),
+        ],
+        Some(ty),
+        BTreeSet::new(),
+        false,
+    );
+
+    assert_eq!(func.merge_identical_blocks(), 0);
+    assert_eq!(func.body.len(), 4);
+}
+
+#[test]
+fn function_strip_meta_removes_meta_instructions() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+
+    let test_b_uuid = module
+        .find_internal_function_uuid_by_name("factorial_test_b")
+        .unwrap();
+    let test_b = module
+        .get_internal_function_by_uuid_mut(test_b_uuid)
+        .unwrap();
+    let before = test_b.iter().count();
+
+    assert_eq!(test_b.strip_meta().unwrap(), 1);
+    assert_eq!(test_b.iter().count(), before - 1);
+    assert!(!test_b.should_be_meta_function());
+    assert!(test_b.iter().all(|(instr, _)| !instr.is_meta_instruction()));
+    test_b.verify().unwrap();
+}
+
+#[test]
+fn function_strip_meta_rejects_used_meta_results() {
+    let reg = registry();
+    let mut module = Module::default();
+    let src = r#"
+define i1 !uses_isdef(%value: i32) {
+entry:
+    %flag: i1 = !isdef %value
+    %both: i1 = and %flag, %flag
+    ret %both
+}
+"#;
+    extend_module_from_string(&mut module, &reg, src).unwrap();
+    let uuid = module
+        .find_internal_function_uuid_by_name("uses_isdef")
+        .unwrap();
+    let func = module.get_internal_function_by_uuid_mut(uuid).unwrap();
+
+    let err = func.strip_meta().unwrap_err();
+    assert!(err.is_meta_result_in_use());
+    assert_eq!(func.iter().count(), 2);
+
+    // The meta instruction is kept when comparing modules
+    assert!(module.equal_ignoring_meta(&module.clone()));
+}
+
+#[test]
+fn module_equal_ignoring_meta_skips_assertions() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+
+    let factorial_uuid = module
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+
+    // Annotate factorial with an extra assertion
+    let mut annotated = module.clone();
+    {
+        let factorial = Arc::make_mut(annotated.functions.get_mut(&factorial_uuid).unwrap());
+        let entry = factorial.body.get_mut(&Label::NIL).unwrap();
+        let cond = entry.instructions[0].destination().unwrap();
+        entry.instructions.push(HyInstr::from(MetaAssert {
+            condition: Operand::Reg(cond),
+        }));
+        factorial.meta_function = true;
+    }
+    assert!(module.equal_ignoring_meta(&annotated));
+    assert!(annotated.equal_ignoring_meta(&module));
+
+    // Changing executable code breaks the equality
+    let mut modified = module.clone();
+    {
+        let factorial = Arc::make_mut(modified.functions.get_mut(&factorial_uuid).unwrap());
+        factorial.substitute_operand(Name(0), Operand::Imm(1u32.into()));
+    }
+    assert!(!module.equal_ignoring_meta(&modified));
+}
+
+#[test]
+fn module_equal_ignoring_meta_matches_separately_parsed_modules() {
+    const PLAIN: &str = r#"
+declare i32 external abs(i32)
+
+define i32 leaf(%x: i32) {
+entry:
+    %y: i32 = invoke ptr external abs, %x
+    ret %y
+}
+
+define i32 caller(%a: i32) {
+entry:
+    %r: i32 = invoke ptr leaf, %a
+    ret %r
+}
+"#;
+    const ANNOTATED: &str = r#"
+declare i32 external abs(i32)
+
+define i32 !leaf(%x: i32) {
+entry:
+    %defined: i1 = !isdef %x
+    !assume %defined
+    %y: i32 = invoke ptr external abs, %x
+    ret %y
+}
+
+define i32 caller(%a: i32) {
+entry:
+    %r: i32 = invoke ptr leaf, %a
+    ret %r
+}
+"#;
+
+    let reg = registry();
+    let parse = |source: &str| {
+        let mut module = Module::default();
+        extend_module_from_string(&mut module, &reg, source).unwrap();
+        module
+    };
+
+    let plain = parse(PLAIN);
+    assert!(plain.equal_ignoring_meta(&parse(PLAIN)));
+    assert!(plain.equal_ignoring_meta(&parse(ANNOTATED)));
+    assert!(parse(ANNOTATED).equal_ignoring_meta(&plain));
+    assert!(!plain.equal_ignoring_meta(&parse(&PLAIN.replace("ptr leaf", "ptr external abs"))));
+    assert!(!plain.equal_ignoring_meta(&parse(&PLAIN.replace("abs", "labs"))));
+}
+
+#[test]
+fn function_derive_successors_on_factorial() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+
+    let uuid = module
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+    let factorial = module.get_internal_function_by_uuid(uuid).unwrap();
+
+    let return_result = factorial
+        .body
+        .values()
+        .find(|bb| bb.instructions.first().is_some_and(|i| i.is_phi()))
+        .unwrap()
+        .label;
+    let recurse = factorial
+        .body
+        .keys()
+        .copied()
+        .find(|label| *label != Label::NIL && *label != return_result)
+        .unwrap();
+
+    let successors = factorial.derive_successors();
+    assert_eq!(successors.len(), 3);
+    assert_eq!(successors[&Label::NIL], vec![return_result, recurse]);
+    assert_eq!(successors[&recurse], vec![return_result]);
+    assert!(successors[&return_result].is_empty());
+}
+
+#[test]
+fn parser_records_instruction_spans() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+
+    let uuid = module
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+    let factorial = module.get_internal_function_by_uuid(uuid).unwrap();
+    let first = InstructionRef::from((Label::NIL, 0));
+    let span = factorial.span_of(first).unwrap();
+    assert_eq!(&FACTORIAL_IR[span.clone()], "%cmp1: i1 = icmp.eq %n, i32 0");
+
+    // Renaming does not move instructions, spans are preserved
+    let mut renamed = factorial.clone();
+    renamed.normalize_ssa();
+    assert_eq!(renamed.span_of(first), Some(span));
+
+    // Removing an instruction shifts the spans of the following ones
+    let uuid = module
+        .find_internal_function_uuid_by_name("factorial_test_a")
+        .unwrap();
+    let mut test_a = module.get_internal_function_by_uuid(uuid).unwrap().clone();
+    assert_eq!(test_a.strip_meta().unwrap(), 2);
+    let span = test_a
+        .span_of(InstructionRef::from((Label::NIL, 2)))
+        .unwrap();
+    assert_eq!(
+        &FACTORIAL_IR[span],
+        "%fact_n: i32 = invoke ptr factorial, %n"
+    );
+    assert!(
+        test_a
+            .span_of(InstructionRef::from((Label::NIL, 6)))
+            .is_none()
+    );
+}
+
+#[test]
+fn function_hash_ignores_spans() {
+    let hash = |function: &Function| {
+        let mut hasher = DefaultHasher::new();
+        function.hash(&mut hasher);
+        hasher.finish()
+    };
+    let factorial = |source: &str| {
+        let reg = registry();
+        let mut module = Module::default();
+        extend_module_from_string(&mut module, &reg, source).unwrap();
+        let uuid = module
+            .find_internal_function_uuid_by_name("factorial")
+            .unwrap();
+        module.get_internal_function_by_uuid(uuid).unwrap().clone()
+    };
+
+    // The same function parsed from differently formatted text
+    let compact = factorial(FACTORIAL_IR);
+    let mut indented = compact.clone();
+    indented.spans = factorial(&format!("\n\n{}", FACTORIAL_IR)).spans;
+    assert_ne!(compact.spans, indented.spans);
+    assert_eq!(hash(&compact), hash(&indented));
+
+    let mut modified = compact.clone();
+    modified.substitute_operand(Name(0), Operand::Imm(1u32.into()));
+    assert_ne!(hash(&compact), hash(&modified));
+}
+
+#[test]
+fn function_split_critical_edges_on_diamond_with_loop() {
+    let reg = registry();
+    let ty = i32(&reg);
+
+    // block_0 -> { block_1, block_3 }, block_1 -> block_3, block_3 -> { block_3, block_4 }
+    // Critical edges: block_0 -> block_3 and the self loop block_3 -> block_3
+    let mut func = function(
+        "critical",
+        vec![(Name(0), i1(&reg))],
+        vec![
+            block(
+                Label::NIL,
+                vec![],
+                HyTerminator::from(Branch {
+                    cond: Operand::Reg(Name(0)),
+                    target_true: Label(1),
+                    target_false: Label(3),
+                }),
+            ),
+            block(
+                Label(1),
+                vec![],
+                HyTerminator::from(Jump { target: Label(3) }),
+            ),
+            block(
+                Label(3),
+                vec![
+                    HyInstr::from(Phi {
+                        dest: Name(1),
+                        ty,
+                        values: vec![
+                            (Operand::Imm(0u32.into()), Label::NIL),
+                            (Operand::Imm(1u32.into()), Label(1)),
+                            (Operand::Reg(Name(2)), Label(3)),
+                        ],
+                    }),
+                    HyInstr::from(IAdd {
+                        dest: Name(2),
+                        ty,
+                        lhs: Operand::Reg(Name(1)),
+                        rhs: Operand::Imm(1u32.into()),
+                        variant: OverflowSignednessPolicy::Wrap,
+                    }),
+                ],
+                HyTerminator::from(Branch {
+                    cond: Operand::Reg(Name(0)),
+                    target_true: Label(3),
+                    target_false: Label(4),
+                }),
+            ),
+            block(
+                Label(4),
+                vec![],
+                HyTerminator::from(Ret {
+                    value: Some(Operand::Reg(Name(1))),
+                }),
+            ),
+        ],
+        Some(ty),
+        BTreeSet::new(),
+        false,
+    );
+
+    assert!(func.verify().is_ok());
+    assert_eq!(func.split_critical_edges(), 2);
+    assert!(func.verify().is_ok());
+    assert_eq!(func.body.len(), 6);
+
+    let successors = func.derive_successors();
+    let entry_split = successors[&Label::NIL][1];
+    let loop_split = successors[&Label(3)][0];
+    assert!(![Label(1), Label(3), Label(4)].contains(&entry_split));
+    assert!(![Label(3), Label(4), entry_split].contains(&loop_split));
+    assert_eq!(successors[&entry_split], vec![Label(3)]);
+    assert_eq!(successors[&loop_split], vec![Label(3)]);
+    assert!(func.body[&entry_split].instructions.is_empty());
+
+    let phi = func.body[&Label(3)].instructions[0]
+        .try_as_phi_ref()
+        .unwrap();
+    assert_eq!(
+        phi.values
+            .iter()
+            .map(|(_, label)| *label)
+            .collect::<Vec<_>>(),
+        vec![entry_split, Label(1), loop_split]
+    );
+
+    // No critical edge remains
+    assert_eq!(func.split_critical_edges(), 0);
+}
+
+#[test]
+fn function_post_dominators_on_diamond() {
+    let func = diamond_function(&registry());
+
+    let post_dominators = func.derive_post_dominators();
+    assert_eq!(
+        post_dominators,
+        BTreeMap::from([
+            (Label::NIL, Label(3)),
+            (Label(1), Label(3)),
+            (Label(2), Label(3)),
+        ])
+    );
+}
+
+#[test]
+fn function_post_dominators_on_loop() {
+    let func = loop_function(&registry());
+
+    let post_dominators = func.derive_post_dominators();
+    assert_eq!(
+        post_dominators,
+        BTreeMap::from([
+            (Label::NIL, Label(1)),
+            (Label(1), Label(3)),
+            (Label(2), Label(1)),
+        ])
+    );
+}
+
+#[test]
+fn function_dominator_tree_on_diamond() {
+    let func = diamond_function(&registry());
+
+    assert_eq!(
+        func.derive_dominator_tree(),
+        BTreeMap::from([
+            (Label::NIL, Label::NIL),
+            (Label(1), Label::NIL),
+            (Label(2), Label::NIL),
+            (Label(3), Label::NIL),
+        ])
+    );
+}
+
+#[test]
+fn function_dominator_tree_on_loop() {
+    let mut func = loop_function(&registry());
+    // Unreachable blocks have no dominator
+    func.body
+        .insert(Label(4), block(Label(4), vec![], HyTerminator::from(Trap)));
+
+    assert_eq!(
+        func.derive_dominator_tree(),
+        BTreeMap::from([
+            (Label::NIL, Label::NIL),
+            (Label(1), Label::NIL),
+            (Label(2), Label(1)),
+            (Label(3), Label(1)),
+        ])
+    );
+}
+
+#[test]
+fn module_unresolved_externals_skips_internally_defined() {
+    let mut module = Module::default();
+    let external = |name: &str| ExternalFunction {
+        uuid: Uuid::new_v4(),
+        name: name.to_string(),
+        cconv: CallingConvention::default(),
+        param_types: vec![],
+        return_type: None,
+    };
+    let defined = external("helper");
+    let undefined = external("puts");
+
+    let call = |uuid: Uuid| {
+        HyInstr::from(Invoke {
+            function: Operand::Imm(AnyConst::FuncPtr(FunctionPointer::External(uuid))),
+            args: vec![],
+            dest: None,
+            ty: None,
+            cconv: None,
+        })
+    };
+    let caller = function(
+        "caller",
+        vec![],
+        vec![block(
+            Label::NIL,
+            vec![call(defined.uuid), call(undefined.uuid)],
+            HyTerminator::from(Ret { value: None }),
+        )],
+        None,
+        BTreeSet::new(),
+        false,
+    );
+    let helper = function(
+        "helper",
+        vec![],
+        vec![block(
+            Label::NIL,
+            vec![],
+            HyTerminator::from(Ret { value: None }),
+        )],
+        None,
+        BTreeSet::new(),
+        false,
+    );
+
+    module.functions.insert(caller.uuid, Arc::new(caller));
+    module.functions.insert(helper.uuid, Arc::new(helper));
+    module.external_functions.insert(defined.uuid, defined);
+    module
+        .external_functions
+        .insert(undefined.uuid, undefined.clone());
+
+    assert_eq!(module.unresolved_externals(), vec![undefined.uuid]);
+    assert_eq!(
+        module.external_symbol_names(),
+        BTreeMap::from([(undefined.uuid, "puts".to_string())])
+    );
+}
+
+#[test]
+fn function_is_pure_follows_calls() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+    extend_module_from_string(
+        &mut module,
+        &reg,
+        r#"
+define i32 write (%dst: ptr, %value: i32) {
+entry:
+    store %dst, %value
+    ret %value
+}
+
+define i32 calls_write (%dst: ptr) {
+entry:
+    %r: i32 = invoke ptr write, %dst, i32 1
+    ret %r
+}
+"#,
+    )
+    .unwrap();
+
+    let get = |name: &str| {
+        let uuid = module.find_internal_function_uuid_by_name(name).unwrap();
+        module.get_internal_function_by_uuid(uuid).unwrap()
+    };
+
+    // Recursive arithmetic function
+    assert!(get("factorial").is_pure(&module));
+    assert!(simple_ok_function(&reg).is_pure(&module));
+
+    // Direct and transitive memory writes
+    assert!(!get("write").is_pure(&module));
+    assert!(!get("calls_write").is_pure(&module));
+}
+
+#[test]
+fn function_verify_construction_reports_all_errors() {
+    let reg = registry();
+    let ty = i32(&reg);
+
+    // block_2 was added as a predecessor of block_3 without updating its phi,
+    // and block_4 is left unreachable.
+    let func = function(
+        "under_construction",
+        vec![(Name(0), i1(&reg))],
+        vec![
+            block(
+                Label::NIL,
+                vec![],
+                HyTerminator::from(Branch {
+                    cond: Operand::Reg(Name(0)),
+                    target_true: Label(1),
+                    target_false: Label(2),
+                }),
+            ),
+            block(
+                Label(1),
+                vec![],
+                HyTerminator::from(Jump { target: Label(3) }),
+            ),
+            block(
+                Label(2),
+                vec![],
+                HyTerminator::from(Jump { target: Label(3) }),
+            ),
+            block(
+                Label(3),
+                vec![HyInstr::from(Phi {
+                    dest: Name(1),
+                    ty,
+                    values: vec![(Operand::Imm(1u32.into()), Label(1))],
+                })],
+                HyTerminator::from(Ret {
+                    value: Some(Operand::Reg(Name(1))),
+                }),
+            ),
+            block(Label(4), vec![], HyTerminator::from(Trap)),
+        ],
+        Some(ty),
+        BTreeSet::new(),
+        false,
+    );
+
+    // Regular verification does not catch these
+    assert!(func.verify().is_ok());
+
+    let errors = func.verify_construction().unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().any(|err| matches!(
+        err,
+        Error::UnreachableBlock { label, .. } if *label == Label(4)
+    )));
+    assert!(errors.iter().any(|err| matches!(
+        err,
+        Error::PhiPredecessorMismatch { block, missing, unexpected, .. }
+            if *block == Label(3) && *missing == vec![Label(2)] && unexpected.is_empty()
+    )));
+
+    assert!(simple_ok_function(&reg).verify_construction().is_ok());
+}
+
+#[test]
+fn function_ssa_renaming_to_detects_renumbering() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+    let uuid = module
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+    let factorial = module.get_internal_function_by_uuid(uuid).unwrap();
+
+    let renamed = shift_ssa_names(factorial);
+
+    let renaming = factorial.ssa_renaming_to(&renamed).unwrap();
+    assert_eq!(renaming.len(), 7);
+    assert!(renaming.iter().all(|(from, to)| *to == shift(*from)));
+
+    let inverse = renamed.ssa_renaming_to(factorial).unwrap();
+    assert!(inverse.iter().all(|(from, to)| renaming[to] == *from));
+
+    // A structural change is not a renaming
+    let mut modified = renamed.clone();
+    modified.substitute_operand(Name(7), Operand::Imm(1u32.into()));
+    assert!(factorial.ssa_renaming_to(&modified).is_none());
+}
+
+#[test]
+fn module_finalize_repairs_stale_wildcards() {
+    let reg = registry();
+    let ty = i32(&reg);
+    let add = HyInstr::from(IAdd {
+        dest: Name(20),
+        ty,
+        lhs: Operand::Reg(Name(10)),
+        rhs: Operand::Imm(1u32.into()),
+        variant: OverflowSignednessPolicy::Wrap,
+    });
+    let func = function(
+        "stale",
+        vec![(Name(10), ty)],
+        vec![block(
+            Label::NIL,
+            vec![add],
+            HyTerminator::from(Ret {
+                value: Some(Operand::Reg(Name(20))),
+            }),
+        )],
+        Some(ty),
+        BTreeSet::from([WType { id: 3 }]), // left over from a previous transformation
+        false,
+    );
+    let uuid = func.uuid;
+
+    let mut module = Module::default();
+    module.functions.insert(uuid, Arc::new(func));
+    assert!(matches!(
+        module.verify(),
+        Err(Error::UnsoundWildcardTypes { .. })
+    ));
+
+    module.finalize(&reg).unwrap();
+    assert!(module.verify().is_ok());
+
+    let func = module.get_internal_function_by_uuid(uuid).unwrap();
+    assert!(func.wildcard_types.is_empty());
+    assert_eq!(func.params[0].0, Name(0));
+    assert_eq!(
+        func.get_instruction_by_dest(Name(1))
+            .unwrap()
+            .dependencies()
+            .collect::<Vec<_>>(),
+        vec![Name(0)]
+    );
+}
+
+#[test]
+fn instruction_result_bit_width() {
+    let reg = registry();
+    let add = HyInstr::from(IAdd {
+        dest: Name(1),
+        ty: i32(&reg),
+        lhs: Operand::Reg(Name(0)),
+        rhs: Operand::Imm(1u32.into()),
+        variant: OverflowSignednessPolicy::Wrap,
+    });
+    let cmp = HyInstr::from(ICmp {
+        dest: Name(2),
+        ty: i1(&reg),
+        lhs: Operand::Reg(Name(1)),
+        rhs: Operand::Imm(0u32.into()),
+        variant: ICmpVariant::Eq,
+    });
+    let assert = HyInstr::MetaAssert(MetaAssert {
+        condition: Operand::Reg(Name(2)),
+    });
+
+    assert_eq!(add.result_bit_width(&reg), Some(32));
+    assert_eq!(cmp.result_bit_width(&reg), Some(1));
+    assert_eq!(assert.result_bit_width(&reg), None);
+}
+
+#[test]
+fn instruction_op_algebraic_properties() {
+    // (op, commutative, associative)
+    let table = [
+        (HyInstrOp::IAdd, true, true),
+        (HyInstrOp::ISub, false, false),
+        (HyInstrOp::IMul, true, true),
+        (HyInstrOp::IDiv, false, false),
+        (HyInstrOp::IRem, false, false),
+        (HyInstrOp::ICmp, false, false),
+        (HyInstrOp::ISht, false, false),
+        (HyInstrOp::INeg, false, false),
+        (HyInstrOp::IAnd, true, true),
+        (HyInstrOp::IOr, true, true),
+        (HyInstrOp::IXor, true, true),
+        (HyInstrOp::INot, false, false),
+        (HyInstrOp::IImplies, false, false),
+        (HyInstrOp::IEquiv, true, true),
+        (HyInstrOp::FAdd, true, false),
+        (HyInstrOp::FSub, false, false),
+        (HyInstrOp::FMul, true, false),
+        (HyInstrOp::FDiv, false, false),
+        (HyInstrOp::FRem, false, false),
+        (HyInstrOp::FCmp, false, false),
+        (HyInstrOp::FNeg, false, false),
+        (HyInstrOp::MLoad, false, false),
+        (HyInstrOp::MStore, false, false),
+        (HyInstrOp::MAlloca, false, false),
+        (HyInstrOp::MGetElementPtr, false, false),
+        (HyInstrOp::Invoke, false, false),
+        (HyInstrOp::Phi, false, false),
+        (HyInstrOp::Select, false, false),
+        (HyInstrOp::Cast, false, false),
+        (HyInstrOp::InsertValue, false, false),
+        (HyInstrOp::ExtractValue, false, false),
+        (HyInstrOp::MetaAssert, false, false),
+        (HyInstrOp::MetaAssume, false, false),
+        (HyInstrOp::MetaIsDef, false, false),
+        (HyInstrOp::MetaProb, false, false),
+        (HyInstrOp::MetaAnalysisStat, false, false),
+        (HyInstrOp::MetaForall, false, false),
+    ];
+
+    assert_eq!(table.len(), HyInstrOp::iter().count());
+    for op in HyInstrOp::iter() {
+        let (_, commutative, associative) = table
+            .iter()
+            .find(|(entry, _, _)| *entry == op)
+            .unwrap_or_else(|| panic!("missing `{}` in table", op.opname()));
+        assert_eq!(op.is_commutative(), *commutative, "{}", op.opname());
+        assert_eq!(op.is_associative(), *associative, "{}", op.opname());
+    }
+}
+
+#[test]
+fn function_fingerprint_ignores_ssa_numbering() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+    let uuid = module
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+    let factorial = module.get_internal_function_by_uuid(uuid).unwrap();
+
+    let renamed = shift_ssa_names(factorial);
+    assert_eq!(
+        factorial.fingerprint(&reg, Some(&module)),
+        renamed.fingerprint(&reg, Some(&module))
+    );
+
+    // The same function parsed against another registry gets other typerefs
+    let other_reg = TypeRegistry::new([1; 6]);
+    other_reg.search_or_insert(IType::I64.into());
+    let mut other_module = Module::default();
+    extend_module_from_string(&mut other_module, &other_reg, FACTORIAL_IR).unwrap();
+    let other_uuid = other_module
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+    let other = other_module
+        .get_internal_function_by_uuid(other_uuid)
+        .unwrap();
+    assert_ne!(other.params, factorial.params);
+    assert_eq!(
+        factorial.fingerprint(&reg, Some(&module)),
+        other.fingerprint(&other_reg, Some(&other_module))
+    );
+
+    let mut modified = renamed.clone();
+    modified.substitute_operand(Name(7), Operand::Imm(1u32.into()));
+    assert_ne!(
+        factorial.fingerprint(&reg, Some(&module)),
+        modified.fingerprint(&reg, Some(&module))
+    );
+}
+
+#[test]
+fn function_fingerprint_hashes_callees_by_name() {
+    const IR: &str = r#"
+declare i32 external abs(i32)
+
+define i32 leaf(%x: i32) {
+entry:
+    %y: i32 = invoke ptr external abs, %x
+    ret %y
+}
+
+define i32 caller(%a: i32) {
+entry:
+    %r: i32 = invoke ptr leaf, %a
+    ret %r
+}
+"#;
+
+    let reg = registry();
+    let fingerprint = |source: &str, name: &str| {
+        let mut module = Module::default();
+        extend_module_from_string(&mut module, &reg, source).unwrap();
+        let uuid = module.find_internal_function_uuid_by_name(name).unwrap();
+        let function = module.get_internal_function_by_uuid(uuid).unwrap();
+        function.fingerprint(&reg, Some(&module))
+    };
+
+    // Each parse assigns fresh UUIDs to the callees
+    assert_eq!(fingerprint(IR, "leaf"), fingerprint(IR, "leaf"));
+    assert_eq!(fingerprint(IR, "caller"), fingerprint(IR, "caller"));
+
+    // Calling another function changes the fingerprint
+    let renamed = IR.replace("leaf", "other_leaf").replace("abs", "labs");
+    assert_ne!(fingerprint(IR, "caller"), fingerprint(&renamed, "caller"));
+    assert_ne!(fingerprint(IR, "leaf"), fingerprint(&renamed, "other_leaf"));
+}
+
+#[test]
+fn function_verify_rejects_arity_mismatch() {
+    let reg = registry();
+    let ty = i32(&reg);
+    let with_instr = |instr: HyInstr| {
+        function(
+            "arity",
+            vec![(Name(0), ty)],
+            vec![block(
+                Label::NIL,
+                vec![instr],
+                HyTerminator::from(Ret {
+                    value: Some(Operand::Reg(Name(0))),
+                }),
+            )],
+            Some(ty),
+            BTreeSet::new(),
+            false,
+        )
+    };
+
+    let empty_phi = with_instr(HyInstr::from(Phi {
+        dest: Name(1),
+        ty,
+        values: vec![],
+    }));
+    assert!(matches!(
+        empty_phi.verify(),
+        Err(Error::ArityMismatch { op, expected: 1, found: 0 }) if op == "phi"
+    ));
+
+    let gep_without_index = with_instr(HyInstr::from(MGetElementPtr {
+        dest: Name(1),
+        ty,
+        in_ty: ty,
+        base: Operand::Reg(Name(0)),
+        indices: vec![],
+    }));
+    assert!(matches!(
+        gep_without_index.verify(),
+        Err(Error::ArityMismatch { op, expected: 2, found: 1 }) if op == "getelementptr"
+    ));
+
+    let gep = with_instr(HyInstr::from(MGetElementPtr {
+        dest: Name(1),
+        ty,
+        in_ty: ty,
+        base: Operand::Reg(Name(0)),
+        indices: vec![Operand::Imm(0u32.into())],
+    }));
+    assert!(gep.verify().is_ok());
+}
+
+#[test]
+fn module_diff_reports_changed_and_added_functions() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+    let mut other = Module::default();
+    extend_module_from_string(&mut other, &reg, FACTORIAL_IR).unwrap();
+
+    // Parsing the same source twice yields no difference despite distinct UUIDs
+    let diff = module.diff(&other);
+    assert!(diff.is_empty());
+    assert_eq!(diff.unchanged.len(), 3);
+
+    let uuid = other
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+    let factorial = other.get_internal_function_by_uuid_mut(uuid).unwrap();
+    factorial.substitute_operand(Name(0), Operand::Imm(1u32.into()));
+    let added = simple_ok_function(&reg);
+    other.functions.insert(added.uuid, Arc::new(added));
+
+    let diff = module.diff(&other);
+    assert!(!diff.is_empty());
+    assert_eq!(diff.added, vec!["ok".to_string()]);
+    assert!(diff.removed.is_empty());
+    assert_eq!(diff.changed, vec!["factorial".to_string()]);
+    assert_eq!(diff.unchanged.len(), 2);
+
+    let reverse = other.diff(&module);
+    assert_eq!(reverse.removed, vec!["ok".to_string()]);
+    assert_eq!(reverse.changed, vec!["factorial".to_string()]);
+}
+
+#[test]
+fn function_verify_checks_definitions_dominate_uses() {
+    let reg = registry();
+    let ty = i32(&reg);
+    let add = |dest: u32, lhs: u32, rhs: u32| {
+        HyInstr::from(IAdd {
+            dest: Name(dest),
+            ty,
+            lhs: Operand::Reg(Name(lhs)),
+            rhs: Operand::Imm(rhs.into()),
+            variant: OverflowSignednessPolicy::Wrap,
+        })
+    };
+    let diamond = |exit: Vec<HyInstr>, ret: u32| {
+        function(
+            "diamond",
+            vec![(Name(0), ty)],
+            vec![
+                block(
+                    Label::NIL,
+                    vec![
+                        add(1, 0, 1),
+                        HyInstr::from(ICmp {
+                            dest: Name(2),
+                            ty: i1(&reg),
+                            lhs: Operand::Reg(Name(0)),
+                            rhs: Operand::Imm(0u32.into()),
+                            variant: ICmpVariant::Eq,
+                        }),
+                    ],
+                    HyTerminator::from(Branch {
+                        cond: Operand::Reg(Name(2)),
+                        target_true: Label(1),
+                        target_false: Label(2),
+                    }),
+                ),
+                block(
+                    Label(1),
+                    vec![add(3, 1, 1)],
+                    HyTerminator::from(Jump { target: Label(3) }),
+                ),
+                block(
+                    Label(2),
+                    vec![add(4, 1, 2)],
+                    HyTerminator::from(Jump { target: Label(3) }),
+                ),
+                block(
+                    Label(3),
+                    exit,
+                    HyTerminator::from(Ret {
+                        value: Some(Operand::Reg(Name(ret))),
+                    }),
+                ),
+            ],
+            Some(ty),
+            BTreeSet::new(),
+            false,
+        )
+    };
+
+    // Values flowing through the phi are only required on their incoming edge
+    let phi = HyInstr::from(Phi {
+        dest: Name(5),
+        ty,
+        values: vec![
+            (Operand::Reg(Name(3)), Label(1)),
+            (Operand::Reg(Name(4)), Label(2)),
+        ],
+    });
+    assert!(diamond(vec![phi, add(6, 1, 3)], 6).verify().is_ok());
+
+    // `%3` is only defined when taking the `label_1` branch
+    assert!(matches!(
+        diamond(vec![add(5, 3, 1)], 5).verify(),
+        Err(Error::UseBeforeDef {
+            name: Name(3),
+            block: Label(3)
+        })
+    ));
+
+    // Uses must follow the definition within the same block
+    assert!(matches!(
+        diamond(vec![add(5, 6, 1), add(6, 1, 1)], 5).verify(),
+        Err(Error::UseBeforeDef {
+            name: Name(6),
+            block: Label(3)
+        })
+    ));
+}
+
+#[test]
+fn function_remove_block_repairs_or_rejects() {
+    let reg = registry();
+    let ty = i32(&reg);
+    let ret = || {
+        HyTerminator::from(Ret {
+            value: Some(Operand::Reg(Name(0))),
+        })
+    };
+    let mut func = function(
+        "remove",
+        vec![(Name(0), ty)],
+        vec![
+            block(
+                Label::NIL,
+                vec![],
+                HyTerminator::from(Jump { target: Label(1) }),
+            ),
+            block(Label(1), vec![], ret()),
+            block(Label(2), vec![], ret()),
+        ],
+        Some(ty),
+        BTreeSet::new(),
+        false,
+    );
+
+    assert!(matches!(
+        func.remove_block(Label::NIL),
+        Err(Error::EntryBlockRemoval { .. })
+    ));
+    assert!(matches!(
+        func.remove_block(Label(1)),
+        Err(Error::BlockStillReferenced {
+            label: Label(1),
+            predecessor: Label::NIL,
+            ..
+        })
+    ));
+    assert!(matches!(
+        func.remove_block(Label(3)),
+        Err(Error::UndefinedBasicBlock {
+            label: Label(3),
+            ..
+        })
+    ));
+    assert_eq!(func.body.len(), 3);
+
+    let removed = func.remove_block(Label(2)).unwrap();
+    assert_eq!(removed.label, Label(2));
+    assert_eq!(
+        func.body.keys().copied().collect::<Vec<_>>(),
+        vec![Label::NIL, Label(1)]
+    );
+    assert!(func.verify().is_ok());
+}
+
+#[test]
+fn function_integer_widths_collects_i1_and_i32() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+    let uuid = module
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+    let factorial = module.get_internal_function_by_uuid(uuid).unwrap();
+
+    assert_eq!(factorial.integer_widths(&reg), BTreeSet::from([1, 32]));
+    assert_eq!(
+        simple_ok_function(&reg).integer_widths(&reg),
+        BTreeSet::from([32])
+    );
+}
+
+#[test]
+fn function_remove_unreachable_blocks_drops_orphans() {
+    let reg = registry();
+    let ty = i32(&reg);
+    let mut func = function(
+        "orphan",
+        vec![(Name(0), ty)],
+        vec![
+            block(
+                Label::NIL,
+                vec![],
+                HyTerminator::from(Jump { target: Label(2) }),
+            ),
+            block(
+                Label(1),
+                vec![],
+                HyTerminator::from(Jump { target: Label(2) }),
+            ),
+            block(
+                Label(2),
+                vec![HyInstr::from(Phi {
+                    dest: Name(1),
+                    ty,
+                    values: vec![
+                        (Operand::Reg(Name(0)), Label::NIL),
+                        (Operand::Imm(1u32.into()), Label(1)),
+                    ],
+                })],
+                HyTerminator::from(Ret {
+                    value: Some(Operand::Reg(Name(1))),
+                }),
+            ),
+        ],
+        Some(ty),
+        BTreeSet::new(),
+        false,
+    );
+    assert!(func.verify_construction().is_err());
+
+    assert_eq!(func.remove_unreachable_blocks(), 1);
+    assert!(!func.body.contains_key(&Label(1)));
+    let phi = func.body[&Label(2)].instructions[0]
+        .try_as_phi_ref()
+        .unwrap();
+    assert_eq!(phi.values, vec![(Operand::Reg(Name(0)), Label::NIL)]);
+    assert!(func.verify().is_ok());
+    assert!(func.verify_construction().is_ok());
+
+    assert_eq!(func.remove_unreachable_blocks(), 0);
+}
+
+#[test]
+fn calling_conventions_round_trip_through_parser() {
+    use std::str::FromStr;
+
+    let conventions = CallingConvention::iter()
+        .filter(|cc| !matches!(cc, CallingConvention::Numbered(_)))
+        .chain([
+            CallingConvention::Numbered(0),
+            CallingConvention::Numbered(64),
+        ]);
+
+    for cc in conventions {
+        assert_eq!(CallingConvention::from_str(&cc.to_string()), Ok(cc));
+
+        let source = format!(
+            "define i32 {} identity(%a: i32) {{\nentry:\n   ret %a\n}}\n",
+            cc.to_string()
+        );
+        let reg = registry();
+        let mut module = Module::default();
+        extend_module_from_string(&mut module, &reg, &source).unwrap();
+        let uuid = module
+            .find_internal_function_uuid_by_name("identity")
+            .unwrap();
+        let function = module.get_internal_function_by_uuid(uuid).unwrap();
+        assert_eq!(function.cconv, Some(cc), "parsing `{}`", cc.to_string());
+
+        let printed = function.fmt(&reg, Some(&module)).to_string();
+        let mut reparsed = Module::default();
+        extend_module_from_string(&mut reparsed, &reg, &printed).unwrap();
+        let uuid = reparsed
+            .find_internal_function_uuid_by_name("identity")
+            .unwrap();
+        assert_eq!(
+            reparsed.get_internal_function_by_uuid(uuid).unwrap().cconv,
+            Some(cc),
+            "printed as `{}`",
+            printed
+        );
+    }
+}
+
+#[test]
+fn function_derive_loops() {
+    let reg = registry();
+    let cond = Operand::Reg(Name(0));
+    let jump = |target: u32| {
+        HyTerminator::from(Jump {
+            target: Label(target),
+        })
+    };
+    let branch = |target_true: u32, target_false: u32| {
+        HyTerminator::from(Branch {
+            cond: cond.clone(),
+            target_true: Label(target_true),
+            target_false: Label(target_false),
+        })
+    };
+    let cfg = |blocks: Vec<(u32, HyTerminator)>| {
+        function(
+            "loops",
+            vec![(Name(0), i1(&reg))],
+            blocks
+                .into_iter()
+                .map(|(label, terminator)| block(Label(label), vec![], terminator))
+                .collect(),
+            None,
+            BTreeSet::new(),
+            false,
+        )
+    };
+
+    // Single loop: 0 -> 1 -> 2 -> 1, 1 -> 3
+    let single = cfg(vec![
+        (0, jump(1)),
+        (1, branch(2, 3)),
+        (2, jump(1)),
+        (3, HyTerminator::from(Trap)),
+    ]);
+    assert_eq!(
+        single.derive_loops(),
+        vec![NaturalLoop {
+            header: Label(1),
+            body: BTreeSet::from([Label(1), Label(2)]),
+            back_edge: (Label(2), Label(1)),
+        }]
+    );
+
+    // Nested loops: outer 1 -> 2 -> 3 -> 4 -> 1, inner 3 -> 2
+    let nested = cfg(vec![
+        (0, jump(1)),
+        (1, branch(2, 5)),
+        (2, jump(3)),
+        (3, branch(2, 4)),
+        (4, jump(1)),
+        (5, HyTerminator::from(Trap)),
+    ]);
+    assert_eq!(
+        nested.derive_loops(),
+        vec![
+            NaturalLoop {
+                header: Label(2),
+                body: BTreeSet::from([Label(2), Label(3)]),
+                back_edge: (Label(3), Label(2)),
+            },
+            NaturalLoop {
+                header: Label(1),
+                body: BTreeSet::from([Label(1), Label(2), Label(3), Label(4)]),
+                back_edge: (Label(4), Label(1)),
+            },
+        ]
+    );
+
+    // Both targets of a branch are the same header: a single back-edge
+    let duplicated = cfg(vec![
+        (0, jump(1)),
+        (1, branch(2, 3)),
+        (2, branch(1, 1)),
+        (3, HyTerminator::from(Trap)),
+    ]);
+    assert_eq!(
+        duplicated.derive_loops(),
+        vec![NaturalLoop {
+            header: Label(1),
+            body: BTreeSet::from([Label(1), Label(2)]),
+            back_edge: (Label(2), Label(1)),
+        }]
+    );
+
+    // Irreducible: the cycle 1 <-> 2 is entered from both 1 and 2
+    let irreducible = cfg(vec![
+        (0, branch(1, 2)),
+        (1, jump(2)),
+        (2, branch(1, 3)),
+        (3, HyTerminator::from(Trap)),
+    ]);
+    assert!(irreducible.derive_loops().is_empty());
+}
+
+#[test]
+fn module_inline_function_inlines_leaf_callee() {
+    const IR: &str = r#"
+define i32 increment ( %x: i32 ) {
+entry:
+   %y: i32 = iadd.wrap %x, i32 1
+   ret %y
+}
+
+define i32 caller ( %a: i1, %b: i32 ) {
+entry:
+   branch %a, call, done
+
+call:
+   %r: i32 = invoke ptr increment, %b
+   %s: i32 = imul.wrap %r, %r
+   jump done
+
+done:
+   %t: i32 = phi [ %s, call ], [ %b, entry ]
+   ret %t
+}
+"#;
+
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, IR).unwrap();
+    let caller = module
+        .find_internal_function_uuid_by_name("caller")
+        .unwrap();
+    let call_site = module
+        .get_internal_function_by_uuid(caller)
+        .unwrap()
+        .gather_instructions_by_predicate(|instr| instr.is_invoke())[0];
+
+    module.inline_function(caller, call_site).unwrap();
+
+    let function = module.get_internal_function_by_uuid(caller).unwrap();
+    assert!(
+        function
+            .gather_instructions_by_predicate(|instr| instr.is_invoke())
+            .is_empty()
+    );
+    assert_eq!(function.body.len(), 5);
+    assert!(function.verify().is_ok());
+    assert!(function.verify_construction().is_ok());
+    assert!(function.type_check(&reg).is_ok());
+    assert!(module.verify().is_ok());
+
+    // The call site no longer refers to an invoke
+    assert!(matches!(
+        module.inline_function(caller, call_site),
+        Err(Error::InvalidCallSite { .. })
+    ));
+}
+
+#[test]
+fn module_inline_function_rejects_recursive_callee() {
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, FACTORIAL_IR).unwrap();
+    let uuid = module
+        .find_internal_function_uuid_by_name("factorial")
+        .unwrap();
+    let call_site = module
+        .get_internal_function_by_uuid(uuid)
+        .unwrap()
+        .gather_instructions_by_predicate(|instr| instr.is_invoke())[0];
+
+    assert!(matches!(
+        module.inline_function(uuid, call_site),
+        Err(Error::InlineUnsupported { .. })
+    ));
+}
+
+#[test]
+fn module_inline_function_strips_meta_outside_meta_callers() {
+    use hyinstr::analysis::{AnalysisStatistic, TerminationScope};
+
+    const IR: &str = r#"
+define i32 !checked_increment ( %x: i32 ) {
+entry:
+   %positive: i1 = icmp.ugt %x, i32 0
+   !assert %positive
+   %y: i32 = iadd.wrap %x, i32 1
+   ret %y
+}
+
+define i1 !is_defined ( %x: i32 ) {
+entry:
+   %defined: i1 = !isdef %x
+   ret %defined
+}
+
+define i32 !reaches_done ( %x: i32 ) {
+entry:
+   %t: i32 = !analysis.term.reach done
+   jump done
+
+done:
+   ret %t
+}
+
+define i32 caller ( %a: i32 ) {
+entry:
+   %r: i32 = invoke ptr checked_increment, %a
+   %d: i1 = invoke ptr is_defined, %a
+   ret %r
+}
+
+define i32 !meta_caller ( %a: i32 ) {
+entry:
+   %r: i32 = invoke ptr checked_increment, %a
+   %s: i32 = invoke ptr reaches_done, %a
+   ret %r
+}
+"#;
+
+    let reg = registry();
+    let mut module = Module::default();
+    extend_module_from_string(&mut module, &reg, IR).unwrap();
+    let invokes = |module: &Module, uuid: Uuid| {
+        module
+            .get_internal_function_by_uuid(uuid)
+            .unwrap()
+            .gather_instructions_by_predicate(|instr| instr.is_invoke())
+    };
+    let has_meta = |module: &Module, uuid: Uuid| {
+        module
+            .get_internal_function_by_uuid(uuid)
+            .unwrap()
+            .iter()
+            .any(|(instr, _)| instr.is_meta_instruction())
+    };
+
+    // The assertion is dropped when inlined into a regular function
+    let caller = module
+        .find_internal_function_uuid_by_name("caller")
+        .unwrap();
+    module
+        .inline_function(caller, invokes(&module, caller)[0])
+        .unwrap();
+    assert!(!has_meta(&module, caller));
+    assert!(module.verify().is_ok());
+
+    // `!isdef` computes the returned value, hence cannot be dropped
+    let call_site = invokes(&module, caller)[0];
+    assert!(matches!(
+        module.inline_function(caller, call_site),
+        Err(Error::InlineUnsupported { .. })
+    ));
+
+    // Meta functions keep the assertion
+    let meta_caller = module
+        .find_internal_function_uuid_by_name("meta_caller")
+        .unwrap();
+    module
+        .inline_function(meta_caller, invokes(&module, meta_caller)[0])
+        .unwrap();
+    assert!(has_meta(&module, meta_caller));
+    assert!(module.verify().is_ok());
+
+    // Labels reached by a termination analysis follow the inlined blocks
+    module
+        .inline_function(meta_caller, invokes(&module, meta_caller)[0])
+        .unwrap();
+    assert!(module.verify().is_ok());
+    let function = module.get_internal_function_by_uuid(meta_caller).unwrap();
+    let reached = function
+        .iter()
+        .find_map(|(instr, _)| match instr {
+            HyInstr::MetaAnalysisStat(stat) => match &stat.statistic {
+                AnalysisStatistic::TerminationBehavior(TerminationScope::ReachAny(labels)) => {
+                    Some(labels.clone())
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .unwrap();
+    let jumps_to_reached = function
+        .body
+        .values()
+        .any(|bb| matches!(&bb.terminator, HyTerminator::Jump(jump) if reached == [jump.target]));
+    assert!(jumps_to_reached);
+}
+
+#[test]
+fn module_to_source_round_trips() {
+    let sources = [
+        FACTORIAL_IR,
+        r#"
+define i32 increment ( %x: i32 ) {
+entry:
+   %y: i32 = iadd.wrap %x, i32 1
+   ret %y
+}
+
+define i32 caller ( %a: i1, %b: i32 ) {
+entry:
+   branch %a, call, done
+
+call:
+   %r: i32 = invoke ptr increment, %b
+   %s: i32 = imul.wrap %r, %r
+   jump done
+
+done:
+   %t: i32 = phi [ %s, call ], [ %b, entry ]
+   ret %t
+}
+"#,
+        r#"
+define fp32 dot3(%a: <3 x fp32>, %b: <3 x fp32>) {
+entry:
+    %ax: fp32 = extractvalue %a, i32 0
+    %bx: fp32 = extractvalue %b, i32 0
+    %m0: fp32 = fmul %ax, %bx
+    %s0: fp32 = fadd %m0, fp32 0.5
+    ret %s0
+}
+
+define fp32 first(%p: { ptr, fp32 }) {
+entry:
+    %v: fp32 = extractvalue %p, i32 1
+    ret %v
+}
+
+define void !check() {
+entry:
+    %x: i1 = !forall
+    %n: i32 = !analysis.icnt i32 0x400
+    %t: i32 = !analysis.term.funcexit
+    !assume %x
+    !assert %x
+    ret void
+}
+"#,
+    ];
+
+    for source in sources {
+        let reg = registry();
+        let mut module = Module::default();
+        extend_module_from_string(&mut module, &reg, source).unwrap();
+
+        let printed = module.to_source(&reg);
+        let mut reparsed = Module::default();
+        if let Err(err) = extend_module_from_string(&mut reparsed, &reg, &printed) {
+            panic!("failed to parse:\n{printed}\n{err:?}");
+        }
+
+        assert!(module.diff(&reparsed).is_empty(), "{printed}");
+        assert_eq!(reparsed.to_source(&reg), printed);
+    }
+}
+
+#[test]
+fn canonical_key_sorts_commutative_operands() {
+    let reg = registry();
+    let mut module = Module::default();
+    let src = r#"
+define i1 keys(%a: i32, %b: i32) {
+entry:
+    %add_ab: i32 = iadd.wrap %a, %b
+    %add_ba: i32 = iadd.wrap %b, %a
+    %sub_ab: i32 = isub.wrap %a, %b
+    %sub_ba: i32 = isub.wrap %b, %a
+    %eq_ab: i1 = icmp.eq %a, %b
+    %eq_ba: i1 = icmp.eq %b, %a
+    %lt_ab: i1 = icmp.ult %a, %b
+    %lt_ba: i1 = icmp.ult %b, %a
+    %imm_a: i32 = imul.wrap i32 3, %a
+    %a_imm: i32 = imul.wrap %a, i32 3
+    %imm_23: i32 = iadd.wrap i32 2, i32 3
+    %imm_32: i32 = iadd.wrap i32 3, i32 2
+    ret %eq_ab
+}
+"#;
+    extend_module_from_string(&mut module, &reg, src).unwrap();
+    let uuid = module.find_internal_function_uuid_by_name("keys").unwrap();
+    let func = module.get_internal_function_by_uuid(uuid).unwrap();
+    let keys: Vec<_> = func.body[&Label::NIL]
+        .instructions
+        .iter()
+        .map(HyInstr::canonical_key)
+        .collect();
+
+    assert_eq!(keys[0], keys[1]);
+    assert_ne!(keys[2], keys[3]);
+    assert_eq!(keys[4], keys[5]);
+    assert_ne!(keys[6], keys[7]);
+    assert_eq!(keys[8], keys[9]);
+    assert_eq!(keys[10], keys[11]);
+    assert_ne!(keys[0], keys[2]);
+}
+
+#[test]
+fn function_eliminate_common_subexpressions() {
+    let reg = registry();
+    let mut module = Module::default();
+    let src = r#"
+define i32 cse(%a: i32, %b: i32) {
+entry:
+    %s1: i32 = iadd.wrap %a, %b
+    %s2: i32 = iadd.wrap %b, %a
+    %d1: i32 = isub.wrap %a, %b
+    %d2: i32 = isub.wrap %b, %a
+    %c: i1 = icmp.eq %s1, %s2
+    branch %c, then, else
+
+then:
+    %s3: i32 = iadd.wrap %b, %a
+    %m1: i32 = imul.wrap %s3, %d1
+    jump done
+
+else:
+    %m2: i32 = imul.wrap %d1, %s2
+    %m3: i32 = imul.wrap %s1, %d1
+    jump done
+
+done:
+    %r: i32 = phi [ %m1, then ], [ %m3, else ]
+    %m4: i32 = imul.wrap %d1, %s1
+    %t: i32 = iadd.wrap %r, %d2
+    %u: i32 = iadd.wrap %t, %m4
+    ret %u
+}
+"#;
+    extend_module_from_string(&mut module, &reg, src).unwrap();
+    let uuid = module.find_internal_function_uuid_by_name("cse").unwrap();
+    let mut func = module.get_internal_function_by_uuid(uuid).unwrap().clone();
+
+    // %s2 and %s3 duplicate %s1; %m3 duplicates %m2 within `else`. %m1 and %m4 do
+    // not dominate each other's blocks, isub is not commutative.
+    assert_eq!(func.eliminate_common_subexpressions(), 3);
+    func.verify().unwrap();
+    assert_eq!(func.eliminate_common_subexpressions(), 0);
+
+    let entry = &func.body[&Label::NIL];
+    assert_eq!(entry.instructions.len(), 4);
+    let HyInstr::ICmp(cmp) = &entry.instructions[3] else {
+        panic!("expected icmp");
+    };
+    assert_eq!(cmp.lhs, cmp.rhs);
+
+    let sizes: Vec<_> = func.body.values().map(|bb| bb.instructions.len()).collect();
+    assert_eq!(sizes, vec![4, 1, 1, 4]);
+
+    // The phi now refers to the surviving %m2
+    let else_block = &func.body[&Label(2)];
+    let m2 = else_block.instructions[0].destination().unwrap();
+    let HyInstr::Phi(phi) = &func.body[&Label(3)].instructions[0] else {
+        panic!("expected phi");
+    };
+    assert_eq!(phi.values[1], (Operand::Reg(m2), Label(2)));
+}